@@ -1,100 +1,653 @@
 use crate::alignments::*;
-pub fn align(
+use crate::match_policy::MatchPolicy;
+use crate::substitution_matrix::SubstitutionMatrix;
+/// Global alignment with affine gaps, maximizing the score.
+pub fn align<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+) -> (i64, Alignment) {
+    let subst = |x: &T, y: &T| if x == y { mat_score } else { mism };
+    let label = |x: &T, y: &T| match x == y {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let (gaps, mode) = (
+        [(gap_open, gap_extend)],
+        Mode::SemiGlobal(EndGaps::default()),
+    );
+    let (score, _, aln) = align_by::<_, _, _, 3>(xs, ys, (subst, label), mism, &gaps, mode);
+    (score, aln)
+}
+
+/// Same as `align`, but the bases are compared by `policy`.
+pub fn align_with_policy(
     xs: &[u8],
     ys: &[u8],
     mat_score: i64,
     mism: i64,
     gap_open: i64,
     gap_extend: i64,
+    policy: MatchPolicy,
 ) -> (i64, Alignment) {
-    // (mat,del,ins)
-    let min = mism.min(gap_open).min(gap_extend) * (xs.len() + ys.len() + 9) as i64;
-    let mut dp = vec![vec![[(min, None); 3]; ys.len() + 1]; xs.len() + 1];
-    for i in 1..xs.len() + 1 {
-        dp[i][0][1] = (gap_open + (i - 1) as i64 * gap_extend, Some(1));
-    }
-    for j in 1..ys.len() + 1 {
-        dp[0][j][2] = (gap_open + (j - 1) as i64 * gap_extend, Some(2));
-    }
-    dp[0][0][0] = (0, None);
-    for (i, x) in xs.iter().enumerate().map(|(i, &x)| (i + 1, x)) {
-        for (j, y) in ys.iter().enumerate().map(|(j, &y)| (j + 1, y)) {
-            dp[i][j][0] = {
-                let mat = if x == y { mat_score } else { mism };
-                dp[i - 1][j - 1]
-                    .iter()
-                    .enumerate()
-                    .map(|(state, (score, _))| (score + mat, Some(state)))
-                    .max_by_key(|x| x.0)
-                    .unwrap()
-            };
-            dp[i][j][1] = {
-                dp[i - 1][j]
-                    .iter()
-                    .enumerate()
-                    .map(|(state, (score, _))| match state {
-                        0 | 2 => (score + gap_open, Some(state)),
-                        1 => (score + gap_extend, Some(state)),
-                        _ => panic!(),
-                    })
-                    .max_by_key(|x| x.0)
-                    .unwrap()
-            };
-            dp[i][j][2] = {
-                dp[i][j - 1]
-                    .iter()
-                    .enumerate()
-                    .map(|(state, (score, _))| match state {
-                        0 | 1 => (score + gap_open, Some(state)),
-                        2 => (score + gap_extend, Some(state)),
-                        _ => panic!(),
-                    })
-                    .max_by_key(|x| x.0)
-                    .unwrap()
-            };
+    let subst = |&x: &u8, &y: &u8| {
+        if policy.matches(x, y) {
+            mat_score
+        } else {
+            mism
+        }
+    };
+    let label = |&x: &u8, &y: &u8| match policy.matches(x, y) {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let (gaps, mode) = (
+        [(gap_open, gap_extend)],
+        Mode::SemiGlobal(EndGaps::default()),
+    );
+    let (score, _, aln) = align_by::<_, _, _, 3>(xs, ys, (subst, label), mism, &gaps, mode);
+    (score, aln)
+}
+
+/// Same as `align`, but the pairs of bases are scored by the substitution matrix.
+/// A pair is labeled as a match if `SubstitutionMatrix::is_match` holds for it.
+pub fn align_with_matrix(
+    xs: &[u8],
+    ys: &[u8],
+    matrix: &SubstitutionMatrix,
+    gap_open: i64,
+    gap_extend: i64,
+) -> (i64, Alignment) {
+    let subst = |&x: &u8, &y: &u8| matrix.score(x, y);
+    let label = |&x: &u8, &y: &u8| match matrix.is_match(x, y) {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let (lowest, gaps) = (matrix.lowest(), [(gap_open, gap_extend)]);
+    let mode = Mode::SemiGlobal(EndGaps::default());
+    let (score, _, aln) = align_by::<_, _, _, 3>(xs, ys, (subst, label), lowest, &gaps, mode);
+    (score, aln)
+}
+
+// The cells of the affine gap DP, (score, the state of the previous cell) for each state.
+// The states are the match state, then the deletion and the insertion of each gap piece.
+type Cell<const N: usize> = [(i64, Option<usize>); N];
+
+// The boundary and the end cells of the full DP of `align_by`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    // From the origin to the last cell, except the end gaps flagged as free.
+    SemiGlobal(EndGaps),
+    // From any cell to the best match state, as Smith-Waterman.
+    Local,
+}
+
+// The full DP over the `(gap_open, gap_extend)` gap pieces, with N = 1 + 2 * pieces.len() states.
+// `lowest` is the lowest score of `subst`. `label` labels the aligned pairs as matches or mismatches.
+// Returns the score, the aligned region, and its alignment.
+fn align_by<T, F, L, const N: usize>(
+    xs: &[T],
+    ys: &[T],
+    (subst, label): (F, L),
+    lowest: i64,
+    pieces: &[(i64, i64)],
+    mode: Mode,
+) -> (i64, Region, Alignment)
+where
+    F: Fn(&T, &T) -> i64,
+    L: Fn(&T, &T) -> Op,
+{
+    assert_eq!(N, 1 + 2 * pieces.len());
+    let lowest = pieces.iter().fold(lowest, |acc, &(o, e)| acc.min(o).min(e));
+    let min = lowest * (xs.len() + ys.len() + 9) as i64;
+    let mut dp = vec![vec![[(min, None); N]; ys.len() + 1]; xs.len() + 1];
+    if let Mode::SemiGlobal(end_gaps) = mode {
+        let gap_score = |(open, extend): (i64, i64), len: usize| open + (len - 1) as i64 * extend;
+        for (i, row) in dp.iter_mut().enumerate().skip(1) {
+            match end_gaps.xs_start {
+                true => row[0][0] = (0, None),
+                false => {
+                    for (p, &piece) in pieces.iter().enumerate() {
+                        row[0][2 * p + 1] = (gap_score(piece, i), Some(2 * p + 1));
+                    }
+                }
+            }
+        }
+        for (j, cell) in dp[0].iter_mut().enumerate().skip(1) {
+            match end_gaps.ys_start {
+                true => cell[0] = (0, None),
+                false => {
+                    for (p, &piece) in pieces.iter().enumerate() {
+                        cell[2 * p + 2] = (gap_score(piece, j), Some(2 * p + 2));
+                    }
+                }
+            }
+        }
+        dp[0][0][0] = (0, None);
+    }
+    for (i, x) in xs.iter().enumerate().map(|(i, x)| (i + 1, x)) {
+        for (j, y) in ys.iter().enumerate().map(|(j, y)| (j + 1, y)) {
+            let mat = subst(x, y);
+            let mut cell = next_cell(&dp[i - 1][j - 1], &dp[i - 1][j], &dp[i][j - 1], mat, pieces);
+            if mode == Mode::Local && cell[0].0 <= mat {
+                cell[0] = (mat, None);
+            }
+            dp[i][j] = cell;
         }
     }
-    // Traceback
-    assert!(dp[0][0].iter().all(|x| x.1.is_none()));
-    let (mut xpos, mut ypos) = (xs.len(), ys.len());
-    let (state, &(score, _)) = dp[xpos][ypos]
+    let (xend, yend, state, score) = match mode {
+        Mode::SemiGlobal(end_gaps) => {
+            let last_row = (0..ys.len() + 1).map(|j| (xs.len(), j));
+            let last_col = (0..xs.len() + 1).map(|i| (i, ys.len()));
+            last_row
+                .chain(last_col)
+                .filter(|&(i, j)| end_gaps.is_end(i, j, xs.len(), ys.len()))
+                .flat_map(|(i, j)| (0..N).map(move |st| (i, j, st)))
+                .map(|(i, j, st)| (i, j, st, dp[i][j][st].0))
+                .max_by_key(|x| x.3)
+                .unwrap()
+        }
+        Mode::Local => (1..xs.len() + 1)
+            .flat_map(|i| (1..ys.len() + 1).map(move |j| (i, j)))
+            .fold((0, 0, 0, 0), |best, (i, j)| match best.3 < dp[i][j][0].0 {
+                true => (i, j, 0, dp[i][j][0].0),
+                false => best,
+            }),
+    };
+    let label = |i: usize, j: usize| label(&xs[i], &ys[j]);
+    let (region, aln) = traceback(|i, j| dp[i][j], (xend, yend, state), label);
+    (score, region, aln)
+}
+
+// The cell from its diagonal, upper, and left neighbours, where `mat` scores the aligned pair.
+// A gap state is extended from itself, and opened from any state but the gaps in the same direction.
+fn next_cell<const N: usize>(
+    diag: &Cell<N>,
+    up: &Cell<N>,
+    left: &Cell<N>,
+    mat: i64,
+    pieces: &[(i64, i64)],
+) -> Cell<N> {
+    let gap_from = |prev: &Cell<N>, to: usize| {
+        let (open, extend) = pieces[(to - 1) / 2];
+        prev.iter()
+            .enumerate()
+            .filter(|&(state, _)| state == 0 || state == to || state % 2 != to % 2)
+            .map(|(state, (score, _))| match state == to {
+                true => (score + extend, Some(state)),
+                false => (score + open, Some(state)),
+            })
+            .max_by_key(|x| x.0)
+            .unwrap()
+    };
+    let mut cell = [(0, None); N];
+    cell[0] = diag
         .iter()
         .enumerate()
-        .max_by_key(|x| x.1 .0)
+        .map(|(state, (score, _))| (score + mat, Some(state)))
+        .max_by_key(|x| x.0)
         .unwrap();
+    for (state, slot) in cell.iter_mut().enumerate().skip(1) {
+        *slot = match state % 2 {
+            1 => gap_from(up, state),
+            _ => gap_from(left, state),
+        };
+    }
+    cell
+}
+
+// Traceback from the state `state` of the cell (xend, yend), where `cell(i, j)` is the cell (i, j).
+// A state without a traceback pointer starts the alignment: a match state inside the matrix
+// starts a local alignment with its pair, and any other one is an empty start.
+fn traceback<C, L, const N: usize>(
+    cell: C,
+    (xend, yend, state): (usize, usize, usize),
+    label: L,
+) -> (Region, Alignment)
+where
+    C: Fn(usize, usize) -> Cell<N>,
+    L: Fn(usize, usize) -> Op,
+{
+    let (mut xpos, mut ypos) = (xend, yend);
     let mut ops = vec![];
     let mut state = Some(state);
     while let Some(st) = state {
-        state = dp[xpos][ypos][st].1;
-        if state == None {
+        state = cell(xpos, ypos)[st].1;
+        if state.is_none() && !(st == 0 && 0 < xpos && 0 < ypos) {
             break;
         }
         match st {
             0 => {
                 xpos -= 1;
                 ypos -= 1;
-                if xs[xpos] == ys[ypos] {
-                    ops.push(Op::Match)
-                } else {
-                    ops.push(Op::Mismatch)
-                }
+                ops.push(label(xpos, ypos));
             }
-            1 => {
+            _ if st % 2 == 1 => {
                 xpos -= 1;
                 ops.push(Op::Del);
             }
-            2 => {
+            _ => {
                 ypos -= 1;
                 ops.push(Op::Ins);
             }
-            _ => unreachable!(),
         }
     }
     ops.reverse();
-    let aln = Alignment::new(ops);
+    let region = Region {
+        xstart: xpos,
+        xend,
+        ystart: ypos,
+        yend,
+    };
+    (region, Alignment::new(ops))
+}
+
+/// Alignment in linear memory by the divide-and-conquer of Myers and Miller, with the same scoring as `align`.
+/// Returns the same score as `align`, with an optimal alignment that may differ from it.
+/// Returns `None` if `gap_open > gap_extend`, i.e., opening a gap is cheaper than extending it.
+pub fn align_linear<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+) -> Option<(i64, Alignment)> {
+    if gap_open > gap_extend {
+        return None;
+    }
+    let param = Param {
+        mat_score,
+        mism,
+        gap_open,
+        gap_extend,
+    };
+    let mut ops = Vec::with_capacity(xs.len() + ys.len());
+    let open = gap_open - gap_extend;
+    myers_miller(xs, ys, &param, open, open, &mut ops);
+    let score = ops.iter().enumerate().fold(0, |score, (idx, &op)| {
+        score
+            + match op {
+                Op::Match => mat_score,
+                Op::Mismatch => mism,
+                _ if 0 < idx && ops[idx - 1] == op => gap_extend,
+                _ => gap_open,
+            }
+    });
+    Some((score, Alignment::new(ops)))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Param {
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+}
+
+impl Param {
+    // The score of a gap of length `len`, where the first column costs `open + gap_extend`.
+    fn gap(&self, open: i64, len: usize) -> i64 {
+        match len {
+            0 => 0,
+            _ => open + len as i64 * self.gap_extend,
+        }
+    }
+    fn subst<T: Eq>(&self, x: &T, y: &T) -> i64 {
+        if x == y {
+            self.mat_score
+        } else {
+            self.mism
+        }
+    }
+}
+
+// Align xs and ys, where a deletion at the start (end) costs `start_open` (`end_open`) instead of
+// `gap_open - gap_extend` on top of its extensions. It is zero if the deletion continues outside.
+fn myers_miller<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    param: &Param,
+    start_open: i64,
+    end_open: i64,
+    ops: &mut Vec<Op>,
+) {
+    let open = param.gap_open - param.gap_extend;
+    if ys.is_empty() {
+        ops.resize(ops.len() + xs.len(), Op::Del);
+        return;
+    }
+    if xs.is_empty() {
+        ops.resize(ops.len() + ys.len(), Op::Ins);
+        return;
+    }
+    if xs.len() == 1 {
+        // Either xs[0] is deleted at the better end, or aligned to some ys[j].
+        let del_open = start_open.max(end_open);
+        let del_score = param.gap(del_open, 1) + param.gap(open, ys.len());
+        let (j, mat_score) = ys
+            .iter()
+            .enumerate()
+            .map(|(j, y)| {
+                let ins = param.gap(open, j) + param.gap(open, ys.len() - j - 1);
+                (j, param.subst(&xs[0], y) + ins)
+            })
+            .max_by_key(|x| x.1)
+            .unwrap();
+        if mat_score < del_score {
+            if start_open < end_open {
+                ops.resize(ops.len() + ys.len(), Op::Ins);
+                ops.push(Op::Del);
+            } else {
+                ops.push(Op::Del);
+                ops.resize(ops.len() + ys.len(), Op::Ins);
+            }
+        } else {
+            ops.resize(ops.len() + j, Op::Ins);
+            match xs[0] == ys[j] {
+                true => ops.push(Op::Match),
+                false => ops.push(Op::Mismatch),
+            }
+            ops.resize(ops.len() + ys.len() - j - 1, Op::Ins);
+        }
+        return;
+    }
+    let mid = xs.len() / 2;
+    let fwd = last_row(xs[..mid].iter(), ys.iter(), param, start_open);
+    let rev = last_row(xs[mid..].iter().rev(), ys.iter().rev(), param, end_open);
+    // Either the alignment passes (mid, j) outside a deletion,
+    // or a deletion runs through xs[mid-1] and xs[mid].
+    let (j, through_del, _) = fwd
+        .iter()
+        .zip(rev.iter().rev())
+        .enumerate()
+        .flat_map(|(j, (f, r))| {
+            let pass = f.iter().max().unwrap() + r.iter().max().unwrap();
+            let through_del = f[1] + r[1] - open;
+            [(j, false, pass), (j, true, through_del)]
+        })
+        .max_by_key(|x| x.2)
+        .unwrap();
+    if through_del {
+        myers_miller(&xs[..mid - 1], &ys[..j], param, start_open, 0, ops);
+        ops.extend([Op::Del, Op::Del]);
+        myers_miller(&xs[mid + 1..], &ys[j..], param, 0, end_open, ops);
+    } else {
+        myers_miller(&xs[..mid], &ys[..j], param, start_open, open, ops);
+        myers_miller(&xs[mid..], &ys[j..], param, open, end_open, ops);
+    }
+}
+
+// The last row of the DP matrix, (mat,del,ins) for each cell.
+fn last_row<'a, T, I, J>(xs: I, ys: J, param: &Param, start_open: i64) -> Vec<[i64; 3]>
+where
+    T: Eq + 'a,
+    I: ExactSizeIterator<Item = &'a T>,
+    J: ExactSizeIterator<Item = &'a T> + Clone,
+{
+    let Param {
+        gap_open,
+        gap_extend,
+        ..
+    } = *param;
+    let len = ys.len();
+    let lowest = param.mism.min(gap_open).min(gap_extend);
+    let min = lowest * (xs.len() + len + 9) as i64;
+    let open = gap_open - gap_extend;
+    let mut row = vec![[min; 3]; len + 1];
+    row[0][0] = 0;
+    for (j, cell) in row.iter_mut().enumerate().skip(1) {
+        cell[2] = param.gap(open, j);
+    }
+    for (i, x) in xs.enumerate().map(|(i, x)| (i + 1, x)) {
+        let mut diag = row[0];
+        row[0] = [min, param.gap(start_open, i), min];
+        for (j, y) in ys.clone().enumerate().map(|(j, y)| (j + 1, y)) {
+            let mat = diag.iter().max().unwrap() + param.subst(x, y);
+            diag = row[j];
+            let [up_mat, up_del, up_ins] = row[j];
+            let del = (up_mat.max(up_ins) + gap_open).max(up_del + gap_extend);
+            let [left_mat, left_del, left_ins] = row[j - 1];
+            let ins = (left_mat.max(left_del) + gap_open).max(left_ins + gap_extend);
+            row[j] = [mat, del, ins];
+        }
+    }
+    row
+}
+
+/// Alignment with two-piece affine gaps, as minimap2. A gap of length L scores
+/// the better of `open + (L-1) * extend` over the two `(gap_open, gap_extend)` pieces.
+/// Usually, the first piece has the larger open and the smaller extension, so that long gaps are cheaper.
+pub fn align_dual<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    piece1: (i64, i64),
+    piece2: (i64, i64),
+) -> (i64, Alignment) {
+    // (mat,del1,ins1,del2,ins2)
+    let subst = |x: &T, y: &T| if x == y { mat_score } else { mism };
+    let label = |x: &T, y: &T| match x == y {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let mode = Mode::SemiGlobal(EndGaps::default());
+    let (score, _, aln) =
+        align_by::<_, _, _, 5>(xs, ys, (subst, label), mism, &[piece1, piece2], mode);
     (score, aln)
 }
 
+/// Semi-global alignment with the same scoring as `align`. The end gaps flagged in `end_gaps` are free.
+/// Returns the score, the aligned region, and the alignment of the region only.
+pub fn align_semi_global<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+    end_gaps: EndGaps,
+) -> (i64, Region, Alignment) {
+    let subst = |x: &T, y: &T| if x == y { mat_score } else { mism };
+    let label = |x: &T, y: &T| match x == y {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let (gaps, mode) = ([(gap_open, gap_extend)], Mode::SemiGlobal(end_gaps));
+    align_by::<_, _, _, 3>(xs, ys, (subst, label), mism, &gaps, mode)
+}
+
+/// Overlap alignment, the best-scoring alignment of a suffix and a prefix in the given orientation,
+/// with the same scoring as `align`. The overlap can be of any length, and its score is not bounded,
+/// unlike `edit_dist_overlap` of the edit distance modules, which returns the longest overlap within a distance.
+/// Returns the score, the overlapping region, and its alignment.
+/// The overlap length on each sequence is `region.xlen()` and `region.ylen()`.
+pub fn align_overlap<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+    overlap: Overlap,
+) -> (i64, Region, Alignment) {
+    let end_gaps = overlap.end_gaps();
+    align_semi_global(xs, ys, mat_score, mism, gap_open, gap_extend, end_gaps)
+}
+
+/// The termination conditions of `align_extension`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DropOff {
+    /// The cells scoring more than `x_drop` below the best score so far are pruned,
+    /// and the extension stops when a row has no cell left, as BLAST.
+    pub x_drop: i64,
+    /// The extension stops when the best score of a row falls more than
+    /// `z_drop + |gap_extend| * (the diagonal distance)` below the best score so far, as minimap2.
+    pub z_drop: Option<i64>,
+}
+
+/// Extension alignment from a seed at `(i, j)` in both directions, with the same scoring as `align`.
+/// `xs[i..]` and `ys[j..]` are extended to the right, and the reversed `xs[..i]` and `ys[..j]` to the left.
+/// Each direction ends at its best-scoring cell found before it drops off, and the two halves are scored
+/// independently, so a gap across the seed is opened on both sides.
+/// Returns the sum of the two scores, the aligned region around the seed, and the alignment of the region.
+/// Only the live band of each row is kept, so the memory is proportional to the cells not pruned.
+/// To extend forward only, give the seed `(0, 0)`.
+#[allow(clippy::too_many_arguments)]
+pub fn align_extension<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    (i, j): (usize, usize),
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+    drop_off: DropOff,
+) -> (i64, Region, Alignment) {
+    let (scores, gaps) = ((mat_score, mism), (gap_open, gap_extend));
+    let xs_left: Vec<_> = xs[..i].iter().rev().collect();
+    let ys_left: Vec<_> = ys[..j].iter().rev().collect();
+    let (left, left_region, left_aln) = extend(&xs_left, &ys_left, scores, gaps, drop_off);
+    let (right, right_region, right_aln) = extend(&xs[i..], &ys[j..], scores, gaps, drop_off);
+    let region = Region {
+        xstart: i - left_region.xend,
+        xend: i + right_region.xend,
+        ystart: j - left_region.yend,
+        yend: j + right_region.yend,
+    };
+    let ops = left_aln.ops.iter().rev().chain(right_aln.ops.iter());
+    (left + right, region, Alignment::new(ops.copied().collect()))
+}
+
+// Extension from the start of xs and ys. The region always starts at zero.
+fn extend<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    (mat_score, mism): (i64, i64),
+    (gap_open, gap_extend): (i64, i64),
+    drop_off: DropOff,
+) -> (i64, Region, Alignment) {
+    // (mat,del,ins). The cells not computed or pruned hold `min`.
+    let min = mism.min(gap_open).min(gap_extend) * (xs.len() + ys.len() + 9) as i64;
+    let empty = [(min, None); 3];
+    let mut rows: Vec<BandRow> = vec![];
+    let (mut best, mut best_pos): (i64, _) = (0, (0, 0, 0));
+    for i in 0..xs.len() + 1 {
+        let (best_before, (best_i, best_j, _)) = (best, best_pos);
+        let prev = rows.last();
+        let get = |j: usize| prev.and_then(|row| row.get(j)).unwrap_or(empty);
+        // The range of the live cells in the previous row.
+        let (lo, hi) = prev.map_or((0, 0), |row| (row.start, row.start + row.cells.len() - 1));
+        let mut cells = vec![];
+        let mut live = None;
+        // The best score in this row, and its distance from the diagonal of the best cell so far.
+        let mut row_best = (min, 0);
+        for j in lo..ys.len() + 1 {
+            let diag = if 0 < j { get(j - 1) } else { empty };
+            let left = if lo < j {
+                *cells.last().unwrap()
+            } else {
+                empty
+            };
+            let mat = match 0 < i && 0 < j {
+                true if xs[i - 1] == ys[j - 1] => mat_score,
+                true => mism,
+                false => 0,
+            };
+            let mut cell = next_cell(&diag, &get(j), &left, mat, &[(gap_open, gap_extend)]);
+            if i == 0 && j == 0 {
+                cell[0] = (0, None);
+            }
+            let threshold = best.saturating_sub(drop_off.x_drop);
+            let mut alive = false;
+            for state in cell.iter_mut() {
+                match state.0 < threshold {
+                    true => *state = (min, None),
+                    false => alive = true,
+                }
+            }
+            cells.push(cell);
+            if !alive {
+                // Beyond the previous row, only insertions could reach this cell.
+                if hi < j {
+                    break;
+                }
+                continue;
+            }
+            let (state, &(score, _)) = cell.iter().enumerate().max_by_key(|x| x.1 .0).unwrap();
+            live = Some((live.map_or(j, |(lo, _)| lo), j));
+            // Among the ties, the cell nearest to the diagonal of the best cell.
+            let diag_diff = ((i as i64 - best_i as i64) - (j as i64 - best_j as i64)).abs();
+            if row_best.0 < score || (row_best.0 == score && diag_diff < row_best.1) {
+                row_best = (score, diag_diff);
+            }
+            if best < score {
+                best = score;
+                best_pos = (i, j, state);
+            }
+        }
+        // Keep the live cells only.
+        match live {
+            Some((start, end)) => rows.push(BandRow {
+                start,
+                cells: cells.drain(start - lo..end - lo + 1).collect(),
+            }),
+            None => break,
+        }
+        if let Some(z_drop) = drop_off.z_drop {
+            let (row_best, diag_diff) = row_best;
+            if best_before - row_best > z_drop + gap_extend.abs() * diag_diff {
+                break;
+            }
+        }
+    }
+    // Traceback from the best cell.
+    let label = |i: usize, j: usize| match xs[i] == ys[j] {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let (region, aln) = traceback(|i, j| rows[i].get(j).unwrap(), best_pos, label);
+    (best, region, aln)
+}
+
+// The live cells (i, start..start + cells.len()) of a row of `extend`.
+struct BandRow {
+    start: usize,
+    cells: Vec<Cell<3>>,
+}
+
+impl BandRow {
+    fn get(&self, j: usize) -> Option<Cell<3>> {
+        j.checked_sub(self.start)
+            .and_then(|k| self.cells.get(k))
+            .copied()
+    }
+}
+
+/// Local (Smith-Waterman-Gotoh) alignment with the same scoring as `align`.
+/// Returns the score, the aligned region, and the alignment of the region only.
+/// If no pair of bases has a positive score, the region and the alignment are empty.
+pub fn align_local<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+) -> (i64, Region, Alignment) {
+    let subst = |x: &T, y: &T| if x == y { mat_score } else { mism };
+    let label = |x: &T, y: &T| match x == y {
+        true => Op::Match,
+        false => Op::Mismatch,
+    };
+    let gaps = [(gap_open, gap_extend)];
+    align_by::<_, _, _, 3>(xs, ys, (subst, label), mism, &gaps, Mode::Local)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -138,4 +691,274 @@ mod test {
             .concat()
         );
     }
+    #[test]
+    fn linear_test() {
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.1, 0.05, 0.05);
+        let params = [
+            (0, -1, -1, -1),
+            (2, -2, -8, -1),
+            (1, -4, -6, -1),
+            (3, -4, -6, -2),
+        ];
+        for len in [0, 1, 2, 10, 60] {
+            for _ in 0..20 {
+                let xs = kiley::gen_seq::generate_seq(&mut rng, len);
+                let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+                for (mat, mism, open, ext) in params {
+                    let (score, _) = align(&xs, &ys, mat, mism, open, ext);
+                    let (linear, aln) = align_linear(&xs, &ys, mat, mism, open, ext).unwrap();
+                    assert_eq!(score, linear);
+                    assert_eq!(aln.validate(&xs, &ys), Ok(()));
+                    let (linear, aln) = align_linear(&ys, &xs, mat, mism, open, ext).unwrap();
+                    assert_eq!(score, linear);
+                    assert_eq!(aln.validate(&ys, &xs), Ok(()));
+                }
+            }
+        }
+        assert_eq!(align_linear(b"ACGT", b"AGT", 1, -1, -1, -2), None);
+    }
+    #[test]
+    fn dual_test() {
+        // A long deletion is cheaper with the second piece.
+        let xs = [b"ACGTACGT".to_vec(), vec![b'T'; 20], b"ACGTACGT".to_vec()].concat();
+        let ys = b"ACGTACGTACGTACGT";
+        let (score, aln) = align_dual(&xs, ys, 1, -4, (-4, -2), (-10, -1));
+        assert_eq!(score, 16 - 10 - 19);
+        let expected = [vec![Op::Match; 8], vec![Op::Del; 20], vec![Op::Match; 8]];
+        assert_eq!(aln.ops, expected.concat());
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        let (piece1, piece2) = ((-4, -2), (-12, -1));
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let (single, _) = align(&xs, &ys, 2, -4, -4, -2);
+            let (same, _) = align_dual(&xs, &ys, 2, -4, piece1, piece1);
+            assert_eq!(single, same);
+            let (score, aln) = align_dual(&xs, &ys, 2, -4, piece1, piece2);
+            assert!(single <= score);
+            assert_eq!(aln.validate(&xs, &ys), Ok(()));
+            let gap = |(open, extend): (i64, i64), len: i64| open + (len - 1) * extend;
+            let rescored: i64 = aln
+                .to_cigar()
+                .split_inclusive(|c: char| !c.is_ascii_digit())
+                .map(|run| {
+                    let (len, op) = run.split_at(run.len() - 1);
+                    let len: i64 = len.parse().unwrap();
+                    match op {
+                        "=" => 2 * len,
+                        "X" => -4 * len,
+                        _ => gap(piece1, len).max(gap(piece2, len)),
+                    }
+                })
+                .sum();
+            assert_eq!(rescored, score);
+        }
+    }
+    #[test]
+    fn extension_test() {
+        let xs = b"ACGTACGTAAAAAAAAAAAA";
+        let ys = b"ACGTTCGTCCCCCCCCCCCC";
+        let drop_off = DropOff {
+            x_drop: 5,
+            z_drop: None,
+        };
+        let (score, region, aln) = align_extension(xs, ys, (0, 0), 1, -2, -3, -1, drop_off);
+        assert_eq!(score, 5);
+        assert_eq!((region.xend, region.yend), (8, 8));
+        assert_eq!(aln.to_string(), "====X===");
+        // A seed at (20, 20) extends to the left on the reversed prefixes, and to the right.
+        let rev = |xs: &[u8]| -> Vec<u8> { xs.iter().rev().copied().collect() };
+        let (xs_both, ys_both) = (
+            [rev(xs), xs.to_vec()].concat(),
+            [rev(ys), ys.to_vec()].concat(),
+        );
+        let (score, region, aln) =
+            align_extension(&xs_both, &ys_both, (20, 20), 1, -2, -3, -1, drop_off);
+        assert_eq!(score, 10);
+        assert_eq!((region.xstart, region.xend), (12, 28));
+        assert_eq!((region.ystart, region.yend), (12, 28));
+        assert_eq!(aln.to_string(), "===X========X===");
+        // The four mismatches are recovered by the matches after them, unless it drops off.
+        let xs = b"ACGTACGTGGGGACGTACGTACGT";
+        let ys = b"ACGTACGTCCCCACGTACGTACGT";
+        let x_drop = 1000;
+        let drop_off = DropOff {
+            x_drop,
+            z_drop: None,
+        };
+        let (score, _, _) = align_extension(xs, ys, (0, 0), 1, -2, -3, -1, drop_off);
+        assert_eq!(score, 12);
+        let z_drop = Some(5);
+        let drop_off = DropOff { x_drop, z_drop };
+        let (score, region, _) = align_extension(xs, ys, (0, 0), 1, -2, -3, -1, drop_off);
+        assert_eq!((score, region.xend), (8, 8));
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        let end_gaps = EndGaps {
+            ys_end: true,
+            ..EndGaps::default()
+        };
+        for _ in 0..20 {
+            let template = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let xs = [template.clone(), kiley::gen_seq::generate_seq(&mut rng, 20)].concat();
+            let ys = kiley::gen_seq::introduce_randomness(&template, &mut rng, &prof);
+            let ys = [ys, kiley::gen_seq::generate_seq(&mut rng, 20)].concat();
+            // The best score of the alignments of a prefix of xs and a prefix of ys.
+            let free_end = (0..xs.len() + 1)
+                .map(|i| align_semi_global(&xs[..i], &ys, 2, -4, -4, -2, end_gaps).0)
+                .max()
+                .unwrap();
+            let no_drop = DropOff {
+                x_drop: i64::MAX,
+                z_drop: None,
+            };
+            let (score, _, _) = align_extension(&xs, &ys, (0, 0), 2, -4, -4, -2, no_drop);
+            assert_eq!(score, free_end);
+            for (x_drop, z_drop) in [(20, None), (10, None), (1000, Some(10))] {
+                let drop_off = DropOff { x_drop, z_drop };
+                let (score, region, aln) =
+                    align_extension(&xs, &ys, (0, 0), 2, -4, -4, -2, drop_off);
+                assert!(score <= free_end);
+                assert_eq!((region.xstart, region.ystart), (0, 0));
+                let (xs, ys) = (&xs[..region.xend], &ys[..region.yend]);
+                assert_eq!(aln.validate(xs, ys), Ok(()));
+                assert_eq!(align(xs, ys, 2, -4, -4, -2).0, score);
+            }
+            // From a seed in the middle, the halves make one alignment of the region around it.
+            let (score, region, aln) = align_extension(&xs, &ys, (20, 20), 2, -4, -4, -2, no_drop);
+            assert!(region.xstart <= 20 && 20 <= region.xend);
+            assert!(region.ystart <= 20 && 20 <= region.yend);
+            let xs = &xs[region.xstart..region.xend];
+            let ys = &ys[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+            assert!(score <= align(xs, ys, 2, -4, -4, -2).0);
+        }
+    }
+    #[test]
+    fn matrix_test() {
+        use crate::substitution_matrix::SubstitutionMatrix;
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        let flat = SubstitutionMatrix::dna(2, -2, -2);
+        let ts_tv = SubstitutionMatrix::dna(2, -1, -3);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let (score, aln) = align(&xs, &ys, 2, -2, -8, -1);
+            assert_eq!(align_with_matrix(&xs, &ys, &flat, -8, -1), (score, aln));
+            let (score, aln) = align_with_matrix(&xs, &ys, &ts_tv, -8, -1);
+            assert_eq!(aln.validate(&xs, &ys), Ok(()));
+            let (xr, _, yr) = aln.recover(&xs, &ys);
+            let rescored: i64 = aln
+                .ops
+                .iter()
+                .zip(xr.iter().zip(yr.iter()))
+                .enumerate()
+                .map(|(idx, (&op, (&x, &y)))| match op {
+                    Op::Match | Op::Mismatch => ts_tv.score(x, y),
+                    _ if 0 < idx && aln.ops[idx - 1] == op => -1,
+                    _ => -8,
+                })
+                .sum();
+            assert_eq!(rescored, score);
+        }
+        let blosum62 = SubstitutionMatrix::blosum62();
+        let (score, aln) = align_with_matrix(b"HEAGAWGHEE", b"PAWHEAE", &blosum62, -10, -1);
+        assert_eq!(aln.validate(b"HEAGAWGHEE", b"PAWHEAE"), Ok(()));
+        assert_eq!(score, -2 - 12 + 4 + 11 - 2 - 1 + 5);
+        assert_eq!(aln.to_string(), "XDDD==XXX=");
+        // The pairs are labeled by the matrix, which scores a lowercase letter as its uppercase letter.
+        let (score, aln) = align_with_matrix(b"ACgT", b"AcGT", &ts_tv, -8, -1);
+        assert_eq!((score, aln.to_string()), (8, "====".to_string()));
+    }
+    #[test]
+    fn local_test() {
+        let xs = b"TTTTTACGTACGTTTTT";
+        let ys = b"GGACGTTCGGG";
+        let (score, region, aln) = align_local(xs, ys, 1, -1, -2, -1);
+        assert_eq!(score, 5);
+        assert_eq!((region.xstart, region.xend), (5, 12));
+        assert_eq!((region.ystart, region.yend), (2, 9));
+        assert_eq!(aln.to_string(), "====X==");
+        let (score, region, aln) = align_local(b"AAAA", b"CCC", 1, -1, -2, -1);
+        assert_eq!(score, 0);
+        assert_eq!(region, Region::default());
+        assert!(aln.ops.is_empty());
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let template = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 20);
+            let xs = [xs.clone(), template.clone(), xs].concat();
+            let ys = kiley::gen_seq::introduce_randomness(&template, &mut rng, &prof);
+            let (score, region, aln) = align_local(&xs, &ys, 2, -2, -8, -1);
+            let (global, _) = align(&xs, &ys, 2, -2, -8, -1);
+            assert!(global <= score);
+            let xs = &xs[region.xstart..region.xend];
+            let ys = &ys[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+            let (sub_score, _) = align(xs, ys, 2, -2, -8, -1);
+            assert_eq!(score, sub_score);
+        }
+    }
+    #[test]
+    fn semi_global_test() {
+        let xs = b"TTTTACGTACGTTTT";
+        let ys = b"ACGTTCG";
+        let end_gaps = EndGaps::free_reference();
+        let (score, region, aln) = align_semi_global(xs, ys, 1, -1, -2, -1, end_gaps);
+        assert_eq!(score, 5);
+        assert_eq!((region.xstart, region.xend), (4, 11));
+        assert_eq!((region.ystart, region.yend), (0, 7));
+        assert_eq!(aln.to_string(), "====X==");
+        use rand::{Rng, SeedableRng};
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let ys = &ys[rng.gen_range(0..5)..];
+            let (global, _) = align(&xs, ys, 2, -2, -8, -1);
+            let charged = EndGaps::default();
+            let (score, _, _) = align_semi_global(&xs, ys, 2, -2, -8, -1, charged);
+            assert_eq!(global, score);
+            let end_gaps = EndGaps {
+                xs_start: rng.gen_bool(0.5),
+                xs_end: rng.gen_bool(0.5),
+                ys_start: rng.gen_bool(0.5),
+                ys_end: rng.gen_bool(0.5),
+            };
+            let (score, region, aln) = align_semi_global(&xs, ys, 2, -2, -8, -1, end_gaps);
+            assert!(global <= score);
+            let xs = &xs[region.xstart..region.xend];
+            let ys = &ys[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+        }
+    }
+    #[test]
+    fn overlap_test() {
+        let xs = b"GGGGGGACGTACGT";
+        let ys = b"ACGTTCGTCCCCCCC";
+        let (score, region, aln) = align_overlap(xs, ys, 1, -1, -2, -1, Overlap::SuffixPrefix);
+        assert_eq!(score, 6);
+        assert_eq!((region.xstart, region.xend), (6, 14));
+        assert_eq!((region.ystart, region.yend), (0, 8));
+        assert_eq!(aln.to_string(), "====X===");
+        let (score, region, _) = align_overlap(ys, xs, 1, -1, -2, -1, Overlap::PrefixSuffix);
+        assert_eq!(score, 6);
+        assert_eq!((region.xlen(), region.ylen()), (8, 8));
+    }
 }
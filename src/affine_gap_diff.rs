@@ -0,0 +1,258 @@
+//! Gap-affine wavefront alignment (WFA), the diagonal-transition counterpart of `affine_gap`.
+//! It minimizes a penalty, where a mismatch costs `mism` and a gap of length L costs `gap_open + L * gap_extend`.
+//! The score of `affine_gap::align` is recovered by `to_penalties` and `to_score`.
+//! Unlike `affine_gap`, it works on bytes only.
+use crate::alignments::*;
+
+/// Convert the scores of `affine_gap::align` into the penalties of `align`.
+/// Returns `None` unless `gap_open <= gap_extend`, `mism < mat_score`, and `2 * gap_extend < mat_score`,
+/// i.e., every penalty is non-negative and the mismatch and the gap extension penalties are positive.
+pub fn to_penalties(
+    mat_score: i64,
+    mism: i64,
+    gap_open: i64,
+    gap_extend: i64,
+) -> Option<(u32, u32, u32)> {
+    // As 2 * #match + 2 * #mismatch + #ins + #del = xs.len() + ys.len(),
+    // the doubled score is mat_score * (xs.len() + ys.len()) - (the penalty below).
+    let mism = 2 * (mat_score - mism);
+    let open = 2 * (gap_extend - gap_open);
+    let extend = mat_score - 2 * gap_extend;
+    (0 < mism && 0 <= open && 0 < extend).then_some((mism as u32, open as u32, extend as u32))
+}
+
+/// Convert the penalty of `align` into the score of `affine_gap::align`.
+pub fn to_score(penalty: u32, xlen: usize, ylen: usize, mat_score: i64) -> i64 {
+    (mat_score * (xlen + ylen) as i64 - penalty as i64) / 2
+}
+
+// A wavefront on the diagonals k = j - i in [lo, hi]. Each cell is the furthest reaching j.
+#[derive(Debug, Clone)]
+struct Wavefront {
+    lo: isize,
+    hi: isize,
+    mat: Vec<Option<usize>>,
+    ins: Vec<Option<usize>>,
+    del: Vec<Option<usize>>,
+}
+
+impl Wavefront {
+    fn new(lo: isize, hi: isize) -> Self {
+        let len = (hi - lo + 1) as usize;
+        Self {
+            lo,
+            hi,
+            mat: vec![None; len],
+            ins: vec![None; len],
+            del: vec![None; len],
+        }
+    }
+    fn index(&self, k: isize) -> Option<usize> {
+        (self.lo <= k && k <= self.hi).then(|| (k - self.lo) as usize)
+    }
+    fn mat(&self, k: isize) -> Option<usize> {
+        self.index(k).and_then(|idx| self.mat[idx])
+    }
+    fn ins(&self, k: isize) -> Option<usize> {
+        self.index(k).and_then(|idx| self.ins[idx])
+    }
+    fn del(&self, k: isize) -> Option<usize> {
+        self.index(k).and_then(|idx| self.del[idx])
+    }
+}
+
+/// Gap-affine alignment by wavefronts. xs is the reference, ys is the query.
+/// Returns the minimum penalty and the alignment.
+pub fn align(xs: &[u8], ys: &[u8], mism: u32, gap_open: u32, gap_extend: u32) -> (u32, Alignment) {
+    assert!(0 < mism && 0 < gap_extend);
+    let (xlen, ylen) = (xs.len() as isize, ys.len() as isize);
+    let (mism, open_ext, extend) = (
+        mism as usize,
+        (gap_open + gap_extend) as usize,
+        gap_extend as usize,
+    );
+    // s -> the wavefront with penalty s, if any.
+    let mut wfs: Vec<Option<Wavefront>> = vec![];
+    let mut init = Wavefront::new(0, 0);
+    init.mat[0] = Some(match_len(xs, 0, ys, 0));
+    wfs.push(Some(init));
+    let end_k = ylen - xlen;
+    let reached = |wf: &Wavefront| wf.mat(end_k) == Some(ys.len());
+    while !wfs.last().unwrap().as_ref().map(reached).unwrap_or(false) {
+        let s = wfs.len();
+        let get = |d: usize| s.checked_sub(d).and_then(|t| wfs[t].as_ref());
+        let (mis_wf, open_wf, ext_wf) = (get(mism), get(open_ext), get(extend));
+        let sources = [
+            mis_wf.map(|w| (w.lo, w.hi)),
+            open_wf.map(|w| (w.lo - 1, w.hi + 1)),
+            ext_wf.map(|w| (w.lo - 1, w.hi + 1)),
+        ];
+        let (lo, hi) = match sources
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(|(l1, h1), (l2, h2)| (l1.min(l2), h1.max(h2)))
+        {
+            Some((lo, hi)) => (lo.max(-xlen), hi.min(ylen)),
+            None => {
+                wfs.push(None);
+                continue;
+            }
+        };
+        let mut wf = Wavefront::new(lo, hi);
+        for k in lo..hi + 1 {
+            // The cell (i, j) is valid if it is inside the DP matrix.
+            let valid = |j: usize| j <= ys.len() && 0 <= j as isize - k && j as isize - k <= xlen;
+            let open_ins = open_wf.and_then(|w| w.mat(k - 1));
+            let ext_ins = ext_wf.and_then(|w| w.ins(k - 1));
+            let ins = open_ins.max(ext_ins).map(|j| j + 1).filter(|&j| valid(j));
+            let open_del = open_wf.and_then(|w| w.mat(k + 1));
+            let ext_del = ext_wf.and_then(|w| w.del(k + 1));
+            let del = open_del.max(ext_del).filter(|&j| valid(j));
+            let mismatch = mis_wf
+                .and_then(|w| w.mat(k))
+                .map(|j| j + 1)
+                .filter(|&j| valid(j));
+            let idx = (k - lo) as usize;
+            wf.ins[idx] = ins;
+            wf.del[idx] = del;
+            wf.mat[idx] = mismatch.max(ins).max(del).map(|j| {
+                let i = (j as isize - k) as usize;
+                j + match_len(xs, i, ys, j)
+            });
+        }
+        wfs.push(Some(wf));
+    }
+    let opt_score = wfs.len() - 1;
+    // Traceback.
+    let (mut s, mut k) = (opt_score, end_k);
+    let mut j = ys.len();
+    let mut state = Op::Match;
+    let mut ops = vec![];
+    let wf_at = |s: usize| wfs[s].as_ref().unwrap();
+    loop {
+        match state {
+            Op::Match => {
+                if s == 0 {
+                    ops.resize(ops.len() + j, Op::Match);
+                    break;
+                }
+                let get = |d: usize| s.checked_sub(d).and_then(|t| wfs[t].as_ref());
+                let mismatch = get(mism).and_then(|w| w.mat(k)).map(|j| j + 1);
+                let wf = wf_at(s);
+                let (ins, del) = (wf.ins(k), wf.del(k));
+                let valid = |j: usize| j <= ys.len() && j as isize - k <= xlen;
+                let mismatch = mismatch.filter(|&j| valid(j));
+                let start = mismatch.max(ins).max(del).unwrap();
+                ops.resize(ops.len() + j - start, Op::Match);
+                j = start;
+                if Some(start) == mismatch {
+                    ops.push(Op::Mismatch);
+                    s -= mism;
+                    j -= 1;
+                } else if Some(start) == ins {
+                    state = Op::Ins;
+                } else {
+                    state = Op::Del;
+                }
+            }
+            Op::Ins => {
+                ops.push(Op::Ins);
+                let open = s
+                    .checked_sub(open_ext)
+                    .and_then(|t| wfs[t].as_ref())
+                    .and_then(|w| w.mat(k - 1));
+                j -= 1;
+                k -= 1;
+                if open == Some(j) {
+                    s -= open_ext;
+                    state = Op::Match;
+                } else {
+                    s -= extend;
+                }
+            }
+            Op::Del => {
+                ops.push(Op::Del);
+                let open = s
+                    .checked_sub(open_ext)
+                    .and_then(|t| wfs[t].as_ref())
+                    .and_then(|w| w.mat(k + 1));
+                k += 1;
+                if open == Some(j) {
+                    s -= open_ext;
+                    state = Op::Match;
+                } else {
+                    s -= extend;
+                }
+            }
+            Op::Mismatch => unreachable!(),
+        }
+    }
+    ops.reverse();
+    (opt_score as u32, Alignment::new(ops))
+}
+
+fn match_len(xs: &[u8], x_start: usize, ys: &[u8], y_start: usize) -> usize {
+    let xs = xs.iter().skip(x_start);
+    let ys = ys.iter().skip(y_start);
+    std::iter::zip(xs, ys).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    #[test]
+    fn align_calc() {
+        let xs = b"AAACCC";
+        let (score, aln) = align(xs, xs, 4, 6, 2);
+        assert_eq!(score, 0);
+        assert_eq!(aln.ops, vec![Op::Match; xs.len()]);
+        let (score, aln) = align(xs, b"AAA", 4, 6, 2);
+        assert_eq!(score, 6 + 3 * 2);
+        assert_eq!(aln.ops, [vec![Op::Match; 3], vec![Op::Del; 3]].concat());
+        let (score, aln) = align(b"", xs, 4, 6, 2);
+        assert_eq!(score, 6 + 6 * 2);
+        assert_eq!(aln.ops, vec![Op::Ins; 6]);
+        let (score, aln) = align(b"ACCCGCCCA", b"AGA", 4, 6, 2);
+        assert_eq!(score, 4 + 6 + 6 * 2);
+        assert_eq!(aln.validate(b"ACCCGCCCA", b"AGA"), Ok(()));
+        assert_eq!(to_penalties(2, -2, -8, -1), Some((8, 14, 4)));
+        assert_eq!(to_penalties(1, -1, -1, -2), None);
+        assert_eq!(to_penalties(1, 1, -2, -1), None);
+        assert_eq!(to_penalties(2, -2, -2, 1), None);
+    }
+    #[test]
+    fn align_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let params = [
+            (2, -2, -8, -1),
+            (1, -1, -1, -1),
+            (0, -1, -3, -1),
+            (3, -4, -6, -2),
+        ];
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+            let seq2 = kiley::gen_seq::introduce_randomness(&seq, &mut rng, &prof);
+            for (mat, mism, open, ext) in params {
+                let (score, _) = crate::affine_gap::align(&seq, &seq2, mat, mism, open, ext);
+                let (x, o, e) = to_penalties(mat, mism, open, ext).unwrap();
+                let (penalty, aln) = align(&seq, &seq2, x, o, e);
+                assert_eq!(to_score(penalty, seq.len(), seq2.len(), mat), score);
+                assert_eq!(aln.validate(&seq, &seq2), Ok(()));
+                let rescored = aln.ops.iter().fold((0, None), |(acc, prev), &op| {
+                    let cost = match op {
+                        Op::Match => 0,
+                        Op::Mismatch => x,
+                        Op::Ins | Op::Del if prev == Some(op) => e,
+                        Op::Ins | Op::Del => o + e,
+                    };
+                    (acc + cost, Some(op))
+                });
+                assert_eq!(rescored.0, penalty);
+            }
+        }
+    }
+}
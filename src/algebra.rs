@@ -0,0 +1,144 @@
+//! This module treats an Alignment as an edit script from the reference to the query,
+//! and defines inversion, composition, concatenation, and slicing on it.
+use crate::alignments::*;
+use crate::liftover::Side;
+
+impl Alignment {
+    /// Swap the reference and the query.
+    pub fn invert(&self) -> Self {
+        let ops = self
+            .ops
+            .iter()
+            .map(|&op| match op {
+                Op::Ins => Op::Del,
+                Op::Del => Op::Ins,
+                op => op,
+            })
+            .collect();
+        Self::new(ops)
+    }
+    /// Compose this alignment, A to B, with `other`, B to C, into the alignment from A to C.
+    /// `None` if this alignment's query and `other`'s reference differ in length.
+    /// As the bases are unknown, two mismatches compose to `Mismatch`. Use `relabel` to fix them.
+    pub fn compose(&self, other: &Self) -> Option<Self> {
+        let mut ops = vec![];
+        let mut first = self.ops.iter().copied().peekable();
+        let mut second = other.ops.iter().copied().peekable();
+        loop {
+            match (first.peek().copied(), second.peek().copied()) {
+                (Some(Op::Del), _) => {
+                    ops.push(Op::Del);
+                    first.next();
+                }
+                (_, Some(Op::Ins)) => {
+                    ops.push(Op::Ins);
+                    second.next();
+                }
+                (Some(a), Some(b)) => {
+                    // Both consume the same base of B.
+                    match (a, b) {
+                        (Op::Ins, Op::Del) => {}
+                        (Op::Ins, _) => ops.push(Op::Ins),
+                        (_, Op::Del) => ops.push(Op::Del),
+                        (Op::Match, Op::Match) => ops.push(Op::Match),
+                        _ => ops.push(Op::Mismatch),
+                    }
+                    first.next();
+                    second.next();
+                }
+                (None, None) => break,
+                _ => return None,
+            }
+        }
+        Some(Self::new(ops))
+    }
+    /// Append the alignment of the segments right after this alignment.
+    pub fn concat(&self, other: &Self) -> Self {
+        let mut ops = self.ops.clone();
+        ops.extend_from_slice(&other.ops);
+        Self::new(ops)
+    }
+    /// The columns aligning the reference interval `[start, end)`, without flanking insertions.
+    /// `None` if the interval is empty or out of range.
+    pub fn slice_ref(&self, start: usize, end: usize) -> Option<Self> {
+        self.slice(start, end, Side::Ref)
+    }
+    /// The columns aligning the query interval `[start, end)`, without flanking deletions.
+    /// `None` if the interval is empty or out of range.
+    pub fn slice_query(&self, start: usize, end: usize) -> Option<Self> {
+        self.slice(start, end, Side::Query)
+    }
+    fn slice(&self, start: usize, end: usize, side: Side) -> Option<Self> {
+        if end <= start {
+            return None;
+        }
+        let first = self.column_of(start, side)?;
+        let last = self.column_of(end - 1, side)?;
+        Some(Self::new(self.ops[first..last + 1].to_vec()))
+    }
+    /// Set `Match` or `Mismatch` on each aligned column by comparing the bases.
+    /// xs is the reference, ys is the query.
+    pub fn relabel(&mut self, xs: &[u8], ys: &[u8]) {
+        let (mut i, mut j) = (0, 0);
+        for op in self.ops.iter_mut() {
+            match op {
+                Op::Match | Op::Mismatch => {
+                    *op = if xs[i] == ys[j] {
+                        Op::Match
+                    } else {
+                        Op::Mismatch
+                    };
+                    i += 1;
+                    j += 1;
+                }
+                Op::Del => i += 1,
+                Op::Ins => j += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    #[test]
+    fn invert_and_concat() {
+        let aln = Alignment::from_str("==XX=IIDD=").unwrap();
+        assert_eq!(aln.invert().to_string(), "==XX=DDII=");
+        assert_eq!(aln.invert().invert(), aln);
+        let other = Alignment::from_str("ID=").unwrap();
+        assert_eq!(aln.concat(&other).to_string(), "==XX=IIDD=ID=");
+    }
+    #[test]
+    fn slice() {
+        let aln = Alignment::from_str("==XX=IIDD=D=").unwrap();
+        assert_eq!(aln.slice_ref(4, 6).unwrap().to_string(), "=IID");
+        assert_eq!(aln.slice_query(4, 8).unwrap().to_string(), "=IIDD=");
+        assert_eq!(aln.slice_query(5, 7).unwrap().to_string(), "II");
+        assert!(aln.slice_ref(5, 11).is_none());
+    }
+    #[test]
+    fn compose() {
+        let ab = Alignment::from_str("=DI=").unwrap();
+        let bc = Alignment::from_str("=I=D").unwrap();
+        assert_eq!(ab.compose(&bc).unwrap().to_string(), "=DIID");
+        assert!(ab.compose(&Alignment::from_str("==").unwrap()).is_none());
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let zs = kiley::gen_seq::introduce_randomness(&ys, &mut rng, &prof);
+            let (_, xy) = crate::edit_dist_usual::edit_dist(&xs, &ys);
+            let (_, yz) = crate::edit_dist_usual::edit_dist(&ys, &zs);
+            let mut xz = xy.compose(&yz).unwrap();
+            xz.relabel(&xs, &zs);
+            assert_eq!(xz.validate(&xs, &zs), Ok(()));
+            let mut xx = xy.compose(&xy.invert()).unwrap();
+            xx.relabel(&xs, &xs);
+            assert_eq!(xx.validate(&xs, &xs), Ok(()));
+        }
+    }
+}
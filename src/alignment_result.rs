@@ -0,0 +1,119 @@
+//! This module defines the serializable result of an alignment.
+//! The alignment is stored as an extended CIGAR string to keep the JSON compact.
+use crate::alignments::*;
+use serde::{Deserialize, Serialize};
+
+/// The aligner producing a result, named after its module.
+/// The other modes of a module, e.g., `edit_dist_linear`, `edit_dist_banded`, or `align_extension`,
+/// are labeled by the module as well. New aligners may add variants.
+/// Each variant lists the entry points taking a `MatchPolicy`. The others compare the bases exactly.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// `affine_gap`. `align_with_policy` takes a policy, and `align_with_matrix` scores by a matrix.
+    /// `align`, `align_linear`, `align_dual`, `align_semi_global`, `align_overlap`, `align_extension`,
+    /// and `align_local` are exact.
+    Affine,
+    /// `affine_gap_diff`. `align` is exact.
+    AffineDiff,
+    /// `edit_dist_bit_parallel`. `dist` and `edit_dist` are exact.
+    EditDistBitParallel,
+    /// `edit_dist_usual`. `edit_dist_with_policy` takes a policy.
+    /// `edit_dist`, `edit_dist_linear`, `edit_dist_bounded`, `edit_dist_semi_global`, and `edit_dist_overlap` are exact.
+    EditDistUsual,
+    /// `edit_dist_usual_clean`. `edit_dist_with_policy` takes a policy. `edit_dist`, `edit_dist_banded`,
+    /// `edit_dist_band_doubling`, `edit_dist_bounded`, `edit_dist_semi_global`, and `edit_dist_overlap` are exact.
+    EditDistUsualClean,
+    /// `edit_dist_diff`. `edit_dist_with_policy` takes a policy.
+    /// `edit_dist`, `edit_dist_rle`, `edit_dist_bounded`, `edit_dist_semi_global`, and `edit_dist_overlap` are exact.
+    EditDistDiff,
+    /// `edit_dist_diff_clean`. `edit_dist_with_policy` takes a policy. `edit_dist`, `edit_dist_rle`,
+    /// `edit_dist_bounded`, `edit_dist_linear`, `edit_dist_semi_global`, and `edit_dist_overlap` are exact.
+    EditDistDiffClean,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AlignmentResult {
+    pub algorithm: Algorithm,
+    /// The score of the aligner. For the edit distance aligners, it is the distance.
+    pub score: i64,
+    /// The number of non-match columns.
+    pub dist: u32,
+    pub num_of_gaps: u32,
+    pub ref_len: usize,
+    pub query_len: usize,
+    #[serde(with = "cigar_string")]
+    pub alignment: Alignment,
+}
+
+impl AlignmentResult {
+    /// xs is the reference, ys is the query.
+    pub fn new(
+        algorithm: Algorithm,
+        score: i64,
+        xs: &[u8],
+        ys: &[u8],
+        alignment: Alignment,
+    ) -> Self {
+        let (dist, num_of_gaps) = alignment.dist_and_num_of_gaps();
+        Self {
+            algorithm,
+            score,
+            dist,
+            num_of_gaps,
+            ref_len: xs.len(),
+            query_len: ys.len(),
+            alignment,
+        }
+    }
+}
+
+/// (De)serialize an Alignment as an extended CIGAR string.
+/// Use it by `#[serde(with = "clean_diff::alignment_result::cigar_string")]`.
+pub mod cigar_string {
+    use crate::alignments::Alignment;
+    use serde::{Deserialize, Deserializer, Serializer};
+    pub fn serialize<S: Serializer>(aln: &Alignment, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&aln.to_cigar())
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Alignment, D::Error> {
+        let cigar = String::deserialize(deserializer)?;
+        Alignment::from_extended_cigar(&cigar).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn json() {
+        let xs = b"ACGTACGT";
+        let ys = b"ACCAAGGT";
+        let (dist, aln) = crate::edit_dist_diff_clean::edit_dist(xs, ys);
+        let result = AlignmentResult::new(Algorithm::EditDistDiffClean, dist as i64, xs, ys, aln);
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains(&format!(
+            "\"alignment\":\"{}\"",
+            result.alignment.to_cigar()
+        )));
+        let recovered: AlignmentResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(recovered, result);
+        let json = json.replace(&result.alignment.to_cigar(), "8M");
+        assert!(serde_json::from_str::<AlignmentResult>(&json).is_err());
+        let (dist, aln) = crate::edit_dist_bit_parallel::edit_dist(xs, ys);
+        let result = AlignmentResult::new(Algorithm::EditDistBitParallel, dist as i64, xs, ys, aln);
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("\"algorithm\":\"EditDistBitParallel\""));
+        assert_eq!(
+            serde_json::from_str::<AlignmentResult>(&json).unwrap(),
+            result
+        );
+    }
+    #[test]
+    fn ops_json() {
+        let aln = Alignment::from_str("=XID").unwrap();
+        let json = serde_json::to_string(&aln).unwrap();
+        assert_eq!(json, r#"{"ops":["Match","Mismatch","Ins","Del"]}"#);
+        assert_eq!(serde_json::from_str::<Alignment>(&json).unwrap(), aln);
+    }
+}
@@ -1,7 +1,9 @@
 //! This module defines the Alignment.
 //!
+use crate::match_policy::MatchPolicy;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+#[derive(Debug, Clone, Eq, PartialEq, Copy, Serialize, Deserialize)]
 pub enum Op {
     Match,
     Mismatch,
@@ -35,7 +37,77 @@ impl std::convert::From<Op> for char {
 
 impl Op {}
 
-#[derive(Debug, Clone)]
+/// The aligned region `xs[xstart..xend]` and `ys[ystart..yend]` of a non-global alignment.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct Region {
+    pub xstart: usize,
+    pub xend: usize,
+    pub ystart: usize,
+    pub yend: usize,
+}
+
+impl Region {
+    /// The length of the aligned region of xs.
+    pub fn xlen(&self) -> usize {
+        self.xend - self.xstart
+    }
+    /// The length of the aligned region of ys.
+    pub fn ylen(&self) -> usize {
+        self.yend - self.ystart
+    }
+}
+
+/// The orientation of an overlap alignment. The end gaps outside of the overlap are free, as `end_gaps`.
+/// Which overlap is returned is documented on each aligner.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Overlap {
+    /// A suffix of xs overlaps a prefix of ys.
+    SuffixPrefix,
+    /// A prefix of xs overlaps a suffix of ys.
+    PrefixSuffix,
+}
+
+impl Overlap {
+    /// The end gaps outside of the overlap are free.
+    pub fn end_gaps(&self) -> EndGaps {
+        let suffix_prefix = *self == Overlap::SuffixPrefix;
+        EndGaps {
+            xs_start: suffix_prefix,
+            xs_end: !suffix_prefix,
+            ys_start: !suffix_prefix,
+            ys_end: suffix_prefix,
+        }
+    }
+}
+
+/// End gaps of a semi-global alignment. `true` means the gap is free, `false` means it is charged as usual.
+/// For example, `xs_start` is the leading overhang of the reference, a run of deletions at the start.
+/// The default charges every end gap, i.e., the global alignment.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct EndGaps {
+    pub xs_start: bool,
+    pub xs_end: bool,
+    pub ys_start: bool,
+    pub ys_end: bool,
+}
+
+impl EndGaps {
+    /// Free reference overhangs on both ends, to align a read against a reference window.
+    pub fn free_reference() -> Self {
+        Self {
+            xs_start: true,
+            xs_end: true,
+            ys_start: false,
+            ys_end: false,
+        }
+    }
+    /// True if an alignment may end at `(i, j)` on xs of length `xlen` and ys of length `ylen`.
+    pub fn is_end(&self, i: usize, j: usize, xlen: usize, ylen: usize) -> bool {
+        (i == xlen || self.xs_end) && (j == ylen || self.ys_end) && (i == xlen || j == ylen)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Alignment {
     pub ops: Vec<Op>,
 }
@@ -70,41 +142,310 @@ impl Alignment {
         }
         (dist, num_of_gap)
     }
-    /// xs is the reference, ys is the query.
-    pub fn recover(&self, xs: &[u8], ys: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
-        let (mut i, mut j) = (0, 0);
-        let (mut xr, mut yr, mut aln) = (vec![], vec![], vec![]);
+    /// Run-length encoded extended CIGAR string, using `=`, `X`, `I`, and `D`.
+    pub fn to_cigar(&self) -> String {
+        let mut cigar = String::new();
+        for (op, len) in self.runs(|op| op) {
+            cigar.push_str(&format!("{}{}", len, char::from(op)));
+        }
+        cigar
+    }
+    /// Run-length encoded classic CIGAR string, where matches and mismatches are both `M`.
+    pub fn to_classic_cigar(&self) -> String {
+        let mut cigar = String::new();
+        let to_classic = |op| match op {
+            Op::Match | Op::Mismatch => 'M',
+            Op::Ins => 'I',
+            Op::Del => 'D',
+        };
+        for (op, len) in self.runs(to_classic) {
+            cigar.push_str(&format!("{}{}", len, op));
+        }
+        cigar
+    }
+    fn runs<T: PartialEq + Copy, F: Fn(Op) -> T>(&self, key: F) -> Vec<(T, usize)> {
+        let mut runs: Vec<(T, usize)> = vec![];
         for &op in self.ops.iter() {
+            let op = key(op);
+            match runs.last_mut() {
+                Some((prev, len)) if *prev == op => *len += 1,
+                _ => runs.push((op, 1)),
+            }
+        }
+        runs
+    }
+    /// Parse an extended (`=`/`X`) or classic (`M`) CIGAR string.
+    /// xs is the reference, ys is the query. They are used to resolve `M` into `Match` or `Mismatch`,
+    /// and the CIGAR string must consume exactly xs and ys.
+    pub fn from_cigar(cigar: &str, xs: &[u8], ys: &[u8]) -> Result<Self, CigarError> {
+        Self::parse_cigar(cigar, Some((xs, ys)))
+    }
+    /// Parse an extended (`=`/`X`) CIGAR string without the sequences. `M` is not supported.
+    pub fn from_extended_cigar(cigar: &str) -> Result<Self, CigarError> {
+        Self::parse_cigar(cigar, None)
+    }
+    fn parse_cigar(cigar: &str, seqs: Option<(&[u8], &[u8])>) -> Result<Self, CigarError> {
+        let mut ops = vec![];
+        let (mut i, mut j): (usize, usize) = (0, 0);
+        let mut len: Option<usize> = None;
+        for (pos, c) in cigar.char_indices() {
+            if let Some(digit) = c.to_digit(10) {
+                let updated = len
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|l| l.checked_add(digit as usize));
+                len = Some(updated.ok_or(CigarError::Overflow { pos })?);
+                continue;
+            }
+            let len = len.take().ok_or(CigarError::MissingLength { pos })?;
+            let (xlen, ylen) = match c {
+                'M' | '=' | 'X' => (len, len),
+                'I' => (0, len),
+                'D' => (len, 0),
+                _ => return Err(CigarError::UnsupportedOp { pos, op: c }),
+            };
+            let (next_i, next_j) = (i.saturating_add(xlen), j.saturating_add(ylen));
+            if let Some((xs, ys)) = seqs {
+                // The number of columns of this operation within the sequences.
+                let fits = match c {
+                    'I' => ys.len() - j,
+                    'D' => xs.len() - i,
+                    _ => (xs.len() - i).min(ys.len() - j),
+                };
+                if fits < len {
+                    let column = ops.len() + fits;
+                    return Err(CigarError::OutOfRange { column });
+                }
+            }
+            match (c, seqs) {
+                ('M', Some((xs, ys))) => {
+                    let pairs = std::iter::zip(&xs[i..next_i], &ys[j..next_j]);
+                    ops.extend(pairs.map(|(x, y)| match x == y {
+                        true => Op::Match,
+                        false => Op::Mismatch,
+                    }));
+                }
+                ('M', None) => return Err(CigarError::UnsupportedOp { pos, op: c }),
+                _ => ops.resize(ops.len() + len, Op::try_from(c).unwrap()),
+            }
+            (i, j) = (next_i, next_j);
+        }
+        if len.is_some() {
+            return Err(CigarError::TrailingLength);
+        }
+        if let Some((xs, ys)) = seqs {
+            if (i, j) != (xs.len(), ys.len()) {
+                let (consumed, lengths) = ((i, j), (xs.len(), ys.len()));
+                return Err(CigarError::LengthMismatch { consumed, lengths });
+            }
+        }
+        Ok(Self { ops })
+    }
+    /// Check that the alignment consumes exactly xs and ys,
+    /// and that every `Match`/`Mismatch` agrees with the bases. xs is the reference, ys is the query.
+    pub fn validate(&self, xs: &[u8], ys: &[u8]) -> Result<(), ValidationError> {
+        self.validate_with_policy(xs, ys, MatchPolicy::Exact)
+    }
+    /// Same as `validate`, but the bases are compared by `policy`.
+    pub fn validate_with_policy(
+        &self,
+        xs: &[u8],
+        ys: &[u8],
+        policy: MatchPolicy,
+    ) -> Result<(), ValidationError> {
+        let (mut i, mut j) = (0, 0);
+        for (column, &op) in self.ops.iter().enumerate() {
+            if op != Op::Ins && xs.len() <= i {
+                return Err(ValidationError::RefOverrun { column });
+            }
+            if op != Op::Del && ys.len() <= j {
+                return Err(ValidationError::QueryOverrun { column });
+            }
             match op {
-                Op::Mismatch | Op::Match => {
-                    xr.push(xs[i]);
-                    yr.push(ys[j]);
-                    if xs[i] == ys[j] {
-                        aln.push(b'|');
-                    } else {
-                        aln.push(b'X');
-                    }
-                    i += 1;
-                    j += 1;
+                Op::Match if !policy.matches(xs[i], ys[j]) => {
+                    return Err(ValidationError::NotMatch {
+                        column,
+                        x: xs[i],
+                        y: ys[j],
+                    })
                 }
-                Op::Del => {
-                    xr.push(xs[i]);
-                    aln.push(b' ');
-                    yr.push(b' ');
-                    i += 1;
+                Op::Mismatch if policy.matches(xs[i], ys[j]) => {
+                    return Err(ValidationError::NotMismatch {
+                        column,
+                        x: xs[i],
+                        y: ys[j],
+                    })
                 }
-                Op::Ins => {
-                    xr.push(b' ');
-                    aln.push(b' ');
-                    yr.push(ys[j]);
-                    j += 1;
+                _ => {}
+            }
+            match op {
+                Op::Match | Op::Mismatch => (i, j) = (i + 1, j + 1),
+                Op::Del => i += 1,
+                Op::Ins => j += 1,
+            }
+        }
+        if i != xs.len() {
+            return Err(ValidationError::RefUnconsumed {
+                consumed: i,
+                len: xs.len(),
+            });
+        }
+        if j != ys.len() {
+            return Err(ValidationError::QueryUnconsumed {
+                consumed: j,
+                len: ys.len(),
+            });
+        }
+        Ok(())
+    }
+    /// xs is the reference, ys is the query.
+    /// It panics if the alignment does not fit the sequences. See `validate`.
+    pub fn recover(&self, xs: &[u8], ys: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        recover_ops(self.ops.iter().copied(), xs, ys, MatchPolicy::Exact)
+    }
+    /// Same as `recover`, but the bases are compared by `policy`.
+    pub fn recover_with_policy(
+        &self,
+        xs: &[u8],
+        ys: &[u8],
+        policy: MatchPolicy,
+    ) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        recover_ops(self.ops.iter().copied(), xs, ys, policy)
+    }
+}
+
+pub(crate) fn recover_ops<I: Iterator<Item = Op>>(
+    ops: I,
+    xs: &[u8],
+    ys: &[u8],
+    policy: MatchPolicy,
+) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let (mut i, mut j) = (0, 0);
+    let (mut xr, mut yr, mut aln) = (vec![], vec![], vec![]);
+    for op in ops {
+        match op {
+            Op::Mismatch | Op::Match => {
+                xr.push(xs[i]);
+                yr.push(ys[j]);
+                if policy.matches(xs[i], ys[j]) {
+                    aln.push(b'|');
+                } else {
+                    aln.push(b'X');
                 }
+                i += 1;
+                j += 1;
+            }
+            Op::Del => {
+                xr.push(xs[i]);
+                aln.push(b' ');
+                yr.push(b' ');
+                i += 1;
+            }
+            Op::Ins => {
+                xr.push(b' ');
+                aln.push(b' ');
+                yr.push(ys[j]);
+                j += 1;
+            }
+        }
+    }
+    (xr, aln, yr)
+}
+
+/// Errors raised when parsing a CIGAR string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CigarError {
+    /// An operation at `pos` has no preceding length.
+    MissingLength { pos: usize },
+    /// The CIGAR string ends with a length but no operation.
+    TrailingLength,
+    /// The length ending at `pos` does not fit in `usize`.
+    Overflow { pos: usize },
+    /// The operation at `pos` is valid SAM but can not be represented by an `Alignment`.
+    UnsupportedOp { pos: usize, op: char },
+    /// An operation at the `column`-th alignment column runs past the end of the sequences.
+    OutOfRange { column: usize },
+    /// The CIGAR string consumes `consumed` bases of xs and ys, while their lengths are `lengths`.
+    LengthMismatch {
+        consumed: (usize, usize),
+        lengths: (usize, usize),
+    },
+}
+
+impl std::fmt::Display for CigarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CigarError::MissingLength { pos } => write!(f, "missing length before {}", pos),
+            CigarError::TrailingLength => write!(f, "length without operation at the end"),
+            CigarError::Overflow { pos } => write!(f, "length overflows at {}", pos),
+            CigarError::UnsupportedOp { pos, op } => {
+                write!(f, "unsupported operation {} at {}", op, pos)
+            }
+            CigarError::OutOfRange { column } => {
+                write!(f, "operation at column {} runs past the sequences", column)
+            }
+            CigarError::LengthMismatch { consumed, lengths } => write!(
+                f,
+                "consumes {} and {} bases of sequences of length {} and {}",
+                consumed.0, consumed.1, lengths.0, lengths.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CigarError {}
+
+/// Errors raised when an Alignment does not fit its sequences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The `column`-th column needs a reference base, but the reference is exhausted.
+    RefOverrun { column: usize },
+    /// The `column`-th column needs a query base, but the query is exhausted.
+    QueryOverrun { column: usize },
+    /// The `column`-th column is `Match`, but the bases differ.
+    NotMatch { column: usize, x: u8, y: u8 },
+    /// The `column`-th column is `Mismatch`, but the bases match.
+    NotMismatch { column: usize, x: u8, y: u8 },
+    /// The alignment ends after `consumed` bases of the reference of length `len`.
+    RefUnconsumed { consumed: usize, len: usize },
+    /// The alignment ends after `consumed` bases of the query of length `len`.
+    QueryUnconsumed { consumed: usize, len: usize },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ValidationError::*;
+        match self {
+            RefOverrun { column } => write!(f, "column {} runs past the reference", column),
+            QueryOverrun { column } => write!(f, "column {} runs past the query", column),
+            NotMatch { column, x, y } => {
+                let (x, y) = (*x as char, *y as char);
+                write!(f, "column {} is a match, but {} != {}", column, x, y)
+            }
+            NotMismatch { column, x, y } => {
+                let (x, y) = (*x as char, *y as char);
+                write!(
+                    f,
+                    "column {} is a mismatch, but {} matches {}",
+                    column, x, y
+                )
+            }
+            RefUnconsumed { consumed, len } => {
+                write!(
+                    f,
+                    "only {} of {} reference bases are aligned",
+                    consumed, len
+                )
+            }
+            QueryUnconsumed { consumed, len } => {
+                write!(f, "only {} of {} query bases are aligned", consumed, len)
             }
         }
-        (xr, aln, yr)
     }
 }
 
+impl std::error::Error for ValidationError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,4 +475,87 @@ mod tests {
         let dg = ops.dist_and_num_of_gaps();
         assert_eq!(dg, (7, 5));
     }
+    #[test]
+    fn cigar() {
+        let aln = Alignment::from_str("==XX=IIDD=").unwrap();
+        assert_eq!(aln.to_cigar(), "2=2X1=2I2D1=");
+        assert_eq!(aln.to_classic_cigar(), "5M2I2D1M");
+        assert_eq!(Alignment::new(vec![]).to_cigar(), "");
+        let xs = b"ACGTACGT";
+        let ys = b"ACCAAGGT";
+        let aln = Alignment::from_cigar("5M2I2D1M", xs, ys).unwrap();
+        assert_eq!(aln.to_string(), "==XX=IIDD=");
+        let aln = Alignment::from_cigar("2=2X1=2I2D1=", xs, ys).unwrap();
+        assert_eq!(aln.to_string(), "==XX=IIDD=");
+        let aln = Alignment::from_cigar("3M1D2M2I", b"ACGTAC", b"ACGACTT").unwrap();
+        assert_eq!(aln.to_string(), "===D==II");
+        assert_eq!(aln.to_classic_cigar(), "3M1D2M2I");
+    }
+    #[test]
+    fn cigar_error() {
+        let xs = b"ACGT";
+        use CigarError::*;
+        let err = |cigar| Alignment::from_cigar(cigar, xs, xs).unwrap_err();
+        assert_eq!(err("M"), MissingLength { pos: 0 });
+        assert_eq!(err("2=D"), MissingLength { pos: 2 });
+        assert_eq!(err("4M2"), TrailingLength);
+        assert_eq!(err("2S2M"), UnsupportedOp { pos: 1, op: 'S' });
+        assert_eq!(err("1D4M"), OutOfRange { column: 4 });
+        assert_eq!(err("2=3D"), OutOfRange { column: 4 });
+        assert_eq!(err("4=1I"), OutOfRange { column: 4 });
+        let lengths = (4, 4);
+        let consumed = (3, 3);
+        assert_eq!(err("3M"), LengthMismatch { consumed, lengths });
+        let consumed = (4, 2);
+        assert_eq!(err("2=2D"), LengthMismatch { consumed, lengths });
+        assert_eq!(
+            Alignment::from_extended_cigar("2=1X"),
+            Ok(Alignment::from_str("==X").unwrap())
+        );
+        assert_eq!(
+            Alignment::from_extended_cigar("3M"),
+            Err(UnsupportedOp { pos: 1, op: 'M' })
+        );
+        assert_eq!(err("99999999999999999999999M"), Overflow { pos: 19 });
+    }
+    #[test]
+    fn validate() {
+        use ValidationError::*;
+        let xs = b"ACGTACGT";
+        let ys = b"ACCAAGGT";
+        let aln = Alignment::from_str("==XX=IIDD=").unwrap();
+        assert_eq!(aln.validate(xs, ys), Ok(()));
+        let aln = Alignment::from_str("===X=IIDD=").unwrap();
+        let err = NotMatch {
+            column: 2,
+            x: b'G',
+            y: b'C',
+        };
+        assert_eq!(aln.validate(xs, ys), Err(err));
+        let aln = Alignment::from_str("X=XX=IIDD=").unwrap();
+        assert_eq!(
+            aln.validate(xs, ys),
+            Err(NotMismatch {
+                column: 0,
+                x: b'A',
+                y: b'A'
+            })
+        );
+        let aln = Alignment::from_str("==XX=IIDD").unwrap();
+        let err = RefUnconsumed {
+            consumed: 7,
+            len: 8,
+        };
+        assert_eq!(aln.validate(xs, ys), Err(err));
+        let aln = Alignment::from_str("==XX=IIDDD").unwrap();
+        let err = QueryUnconsumed {
+            consumed: 7,
+            len: 8,
+        };
+        assert_eq!(aln.validate(xs, ys), Err(err));
+        let aln = Alignment::from_str("==XX=IIDD=D").unwrap();
+        assert_eq!(aln.validate(xs, ys), Err(RefOverrun { column: 10 }));
+        let aln = Alignment::from_str("==XX=IIDD=I").unwrap();
+        assert_eq!(aln.validate(xs, ys), Err(QueryOverrun { column: 10 }));
+    }
 }
@@ -0,0 +1,165 @@
+//! Unit-cost edit distance by Myers' bit-vector algorithm, in the block-based formulation of Hyyrö.
+//! Each column of the DP matrix is encoded as the vertical differences (+1/-1) of adjacent cells,
+//! packed 64 rows to a word, so a column is computed in O(xs.len() / 64) word operations.
+//! The alignment is recovered from the stored columns, as in Hyyrö (2004).
+//! It works on bytes only, as the bit-vectors are indexed by the byte values.
+use crate::alignments::*;
+
+const WORD: usize = 64;
+
+// The bit-vectors of the positions where each byte occurs in xs.
+fn peq(xs: &[u8]) -> Vec<Vec<u64>> {
+    let blocks = xs.len().div_ceil(WORD);
+    let mut peq = vec![vec![0; blocks]; 256];
+    for (i, &x) in xs.iter().enumerate() {
+        peq[x as usize][i / WORD] |= 1 << (i % WORD);
+    }
+    peq
+}
+
+// Advance one block of a column. `hin` is the horizontal difference at the row above the block.
+// Returns the new vertical differences and the horizontal difference at the last row of the block.
+fn advance_block(pv: u64, mv: u64, eq: u64, hin: i8, last: usize) -> (u64, u64, i8) {
+    let xv = eq | mv;
+    let eq = eq | (hin < 0) as u64;
+    let xh = (((eq & pv).wrapping_add(pv)) ^ pv) | eq;
+    let mut ph = mv | !(xh | pv);
+    let mut mh = pv & xh;
+    let hout = ((ph >> last) & 1) as i8 - ((mh >> last) & 1) as i8;
+    ph <<= 1;
+    mh <<= 1;
+    match hin {
+        1 => ph |= 1,
+        -1 => mh |= 1,
+        _ => {}
+    }
+    let pv = mh | !(xv | ph);
+    let mv = ph & xv;
+    (pv, mv, hout)
+}
+
+// The last row of each block.
+fn last_rows(xlen: usize) -> impl Iterator<Item = usize> {
+    let blocks = xlen.div_ceil(WORD);
+    (0..blocks).map(move |b| match b + 1 == blocks {
+        true => (xlen - 1) % WORD,
+        false => WORD - 1,
+    })
+}
+
+/// The edit distance between xs and ys in O(xs.len() / 64) memory.
+pub fn dist(xs: &[u8], ys: &[u8]) -> u32 {
+    if xs.is_empty() {
+        return ys.len() as u32;
+    }
+    let peq = peq(xs);
+    let mut pv = vec![!0; peq[0].len()];
+    let mut mv = vec![0; peq[0].len()];
+    let mut dist = xs.len() as i64;
+    for &y in ys.iter() {
+        // The top row is D[0][j] = j.
+        let mut hin = 1;
+        for (b, last) in last_rows(xs.len()).enumerate() {
+            let (p, m, hout) = advance_block(pv[b], mv[b], peq[y as usize][b], hin, last);
+            (pv[b], mv[b], hin) = (p, m, hout);
+        }
+        dist += hin as i64;
+    }
+    dist as u32
+}
+
+/// Edit distance alignment. xs is the reference, ys is the query.
+/// Every column of vertical differences is kept for the traceback, i.e., O(xs.len() * ys.len() / 64) memory.
+pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
+    if xs.is_empty() || ys.is_empty() {
+        let mut ops = vec![Op::Ins; ys.len()];
+        ops.resize(xs.len() + ys.len(), Op::Del);
+        return (ops.len() as u32, Alignment::new(ops));
+    }
+    let peq = peq(xs);
+    let blocks = peq[0].len();
+    // columns[j] is the vertical differences of the j-th column.
+    let mut columns = vec![(vec![!0; blocks], vec![0; blocks])];
+    for &y in ys.iter() {
+        let (mut pv, mut mv) = columns.last().unwrap().clone();
+        let mut hin = 1;
+        for (b, last) in last_rows(xs.len()).enumerate() {
+            let (p, m, hout) = advance_block(pv[b], mv[b], peq[y as usize][b], hin, last);
+            (pv[b], mv[b], hin) = (p, m, hout);
+        }
+        columns.push((pv, mv));
+    }
+    // D[i][j] = j + (# of +1 in the first i rows) - (# of -1 in the first i rows).
+    let score = |i: usize, j: usize| -> u32 {
+        let (pv, mv) = &columns[j];
+        let count = |bits: &[u64]| -> u32 {
+            let full: u32 = bits[..i / WORD].iter().map(|b| b.count_ones()).sum();
+            let rest = match i % WORD {
+                0 => 0,
+                r => (bits[i / WORD] & ((1 << r) - 1)).count_ones(),
+            };
+            full + rest
+        };
+        j as u32 + count(pv) - count(mv)
+    };
+    let dist = score(xs.len(), ys.len());
+    let (mut i, mut j) = (xs.len(), ys.len());
+    let mut current = dist;
+    let mut ops = vec![];
+    while 0 < i && 0 < j {
+        let mat = xs[i - 1] == ys[j - 1];
+        if score(i - 1, j - 1) + !mat as u32 == current {
+            ops.push(if mat { Op::Match } else { Op::Mismatch });
+            (i, j) = (i - 1, j - 1);
+        } else if score(i, j - 1) + 1 == current {
+            ops.push(Op::Ins);
+            j -= 1;
+        } else {
+            ops.push(Op::Del);
+            i -= 1;
+        }
+        current = score(i, j);
+    }
+    ops.resize(ops.len() + i, Op::Del);
+    ops.resize(ops.len() + j, Op::Ins);
+    ops.reverse();
+    (dist, Alignment::new(ops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    #[test]
+    fn edit_dist_calc() {
+        let xs = b"AAACCC";
+        let (d, aln) = edit_dist(xs, xs);
+        assert_eq!(d, 0);
+        assert_eq!(aln.ops, vec![Op::Match; xs.len()]);
+        let (d, aln) = edit_dist(xs, b"AAA");
+        assert_eq!(d, 3);
+        assert_eq!(aln.ops, [vec![Op::Match; 3], vec![Op::Del; 3]].concat());
+        assert_eq!(edit_dist(xs, b"").0, 6);
+        assert_eq!(edit_dist(b"", xs).1.ops, vec![Op::Ins; 6]);
+        assert_eq!(dist(b"ACGT", b"ACCTG"), 2);
+    }
+    #[test]
+    fn edit_dist_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.1, 0.05, 0.05);
+        for len in [1, 10, 63, 64, 65, 200] {
+            for _ in 0..20 {
+                let xs = kiley::gen_seq::generate_seq(&mut rng, len);
+                let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+                let (expected, _) = crate::edit_dist_usual::edit_dist(&xs, &ys);
+                assert_eq!(dist(&xs, &ys), expected);
+                assert_eq!(dist(&ys, &xs), expected);
+                let (d, aln) = edit_dist(&xs, &ys);
+                assert_eq!(d, expected);
+                assert_eq!(aln.validate(&xs, &ys), Ok(()));
+                assert_eq!(aln.dist_and_num_of_gaps().0, expected);
+            }
+        }
+    }
+}
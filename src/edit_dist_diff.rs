@@ -1,12 +1,55 @@
 use super::alignments::*;
+use crate::match_policy::MatchPolicy;
+use crate::run_length::RunLengthAlignment;
 /// Usual edit distance alignments and its path.
-pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
+pub fn edit_dist<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    let (dist, aln) = edit_dist_rle(xs, ys);
+    (dist, aln.into())
+}
+
+/// Same as `edit_dist`, but the path is built as runs directly from the match snakes.
+pub fn edit_dist_rle<T: Eq>(xs: &[T], ys: &[T]) -> (u32, RunLengthAlignment) {
+    edit_dist_rle_bounded(xs, ys, xs.len() + ys.len(), |x, y| x == y).unwrap()
+}
+
+/// Same as `edit_dist`, but the bases are compared by `policy`.
+pub fn edit_dist_with_policy(xs: &[u8], ys: &[u8], policy: MatchPolicy) -> (u32, Alignment) {
+    let (dist, aln) =
+        edit_dist_rle_bounded(xs, ys, xs.len() + ys.len(), |&x, &y| policy.matches(x, y)).unwrap();
+    (dist, aln.into())
+}
+
+/// Same as `edit_dist`, but returns `None` as soon as the distance turns out to exceed `max_dist`.
+pub fn edit_dist_bounded<T: Eq>(xs: &[T], ys: &[T], max_dist: u32) -> Option<(u32, Alignment)> {
+    let (dist, aln) = edit_dist_rle_bounded(xs, ys, max_dist as usize, |x, y| x == y)?;
+    Some((dist, aln.into()))
+}
+
+fn edit_dist_rle_bounded<T, F>(
+    xs: &[T],
+    ys: &[T],
+    max_dist: usize,
+    eq: F,
+) -> Option<(u32, RunLengthAlignment)>
+where
+    T: Eq,
+    F: Fn(&T, &T) -> bool,
+{
     if xs == ys {
-        return (0, Alignment::new(vec![Op::Match; xs.len()]));
+        return Some((
+            0,
+            RunLengthAlignment::new(vec![(Op::Match, xs.len() as u32)]),
+        ));
+    }
+    if max_dist < xs.len().abs_diff(ys.len()) {
+        return None;
     }
     // h -> d = the furthest reaching point of the d - h diagonal with edit distace h, and the traceback pointer.
-    let mut dp: Vec<Vec<(usize, Option<u8>)>> = vec![vec![(match_len(xs, 0, ys, 0), None)]];
+    let mut dp: Vec<Vec<(usize, Option<u8>)>> = vec![vec![(match_len_by(xs, 0, ys, 0, &eq), None)]];
     'outer: for dist in 1..xs.len() + ys.len() + 1 {
+        if max_dist < dist {
+            return None;
+        }
         let mut new_d = Vec::with_capacity(2 * dist + 1);
         for diag in 0..2 * dist + 1 {
             let from_above = dp[dist - 1].get(diag).map(|&(x, _)| x);
@@ -20,7 +63,7 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
             };
             let (max_reach, trace) = max_three(from_above, from_mat, from_left);
             let i = (max_reach + dist) - diag;
-            let snake = match_len(xs, i, ys, max_reach);
+            let snake = match_len_by(xs, i, ys, max_reach, &eq);
             new_d.push((max_reach + snake, trace));
             let j = max_reach + snake;
             let i = (j + dist) - diag;
@@ -34,7 +77,7 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
     }
     let opt_dist = dp.len() - 1;
     let (mut diag, (mut ypos, mut prev)) = dp.last().unwrap().iter().enumerate().last().unwrap();
-    let mut ops = vec![];
+    let mut ops = RunLengthAlignment::default();
     let mut dist = opt_dist;
     while let Some(trace) = prev {
         let old_ypos = ypos;
@@ -57,22 +100,203 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
             Op::Mismatch | Op::Ins => old_ypos - ypos - 1,
             _ => panic!(),
         };
-        ops.extend(std::iter::repeat(Op::Match).take(len));
-        ops.push(op);
+        ops.push(Op::Match, len as u32);
+        ops.push(op, 1);
     }
     assert_eq!(dist, 0);
     let xpos = (ypos + dist) - diag;
     assert_eq!(xpos, ypos);
-    ops.extend(std::iter::repeat(Op::Match).take(xpos));
+    ops.push(Op::Match, xpos as u32);
     ops.reverse();
-    let aln = Alignment::new(ops);
-    (opt_dist as u32, aln)
+    Some((opt_dist as u32, ops))
 }
 
-fn match_len(xs: &[u8], x_start: usize, ys: &[u8], y_start: usize) -> usize {
+type Wavefront = Vec<Option<(usize, Option<u8>)>>;
+
+/// Semi-global edit distance. The end gaps flagged in `end_gaps` are free.
+/// Returns the distance, the aligned region, and the alignment of the region only.
+pub fn edit_dist_semi_global<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+) -> (u32, Region, Alignment) {
+    let (xlen, ylen) = (xs.len(), ys.len());
+    let reached = |wf: &(usize, Wavefront)| reached_ends(wf, xlen, ylen, end_gaps).next();
+    let dp = semi_global_wavefronts(xs, ys, end_gaps, usize::MAX, |wf| reached(wf).is_some());
+    let (k, _) = reached(dp.last().unwrap()).unwrap();
+    let dist = dp.len() - 1;
+    let (region, aln) = semi_global_traceback(&dp, dist, k, ylen);
+    (dist as u32, region, aln)
+}
+
+/// Same as `edit_dist_usual::edit_dist_overlap`, the longest overlap with at most `max_dist` edits.
+/// Returns the distance, the overlapping region, and its alignment, or `None` if they do not overlap.
+pub fn edit_dist_overlap<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    overlap: Overlap,
+    max_dist: u32,
+) -> Option<(u32, Region, Alignment)> {
+    let (xlen, ylen) = (xs.len(), ys.len());
+    let end_gaps = overlap.end_gaps();
+    let max_dist = (max_dist as usize).min(xlen + ylen);
+    let dp = semi_global_wavefronts(xs, ys, end_gaps, max_dist, |_| false);
+    let overlap_len = |k: usize, j: usize| match overlap {
+        Overlap::SuffixPrefix => j,
+        Overlap::PrefixSuffix => j + k - ylen,
+    };
+    // The longest overlap, at the distance it is reached first.
+    let (_, dist, k) = dp
+        .iter()
+        .enumerate()
+        .flat_map(|(dist, wf)| {
+            reached_ends(wf, xlen, ylen, end_gaps).map(move |(k, j)| (overlap_len(k, j), dist, k))
+        })
+        .filter(|&(len, _, _)| 0 < len)
+        .min_by_key(|&(len, dist, _)| (std::cmp::Reverse(len), dist))?;
+    let (region, aln) = semi_global_traceback(&dp, dist, k, ylen);
+    Some((dist as u32, region, aln))
+}
+
+// The wavefronts of a semi-global alignment.
+// As free start gaps put many diagonals on the wavefront of distance zero,
+// the wavefronts are indexed by k = i - j + ys.len() rather than by h.
+// d -> (lo, k - lo -> the furthest reaching point (j) of the diagonal with edit distance d, and the traceback pointer).
+// The wavefront of distance zero covers the free start diagonals, and it grows by one diagonal per distance.
+// Stops at the first wavefront satisfying `stop`, or at the wavefront of distance `max_dist`.
+fn semi_global_wavefronts<T, F>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+    max_dist: usize,
+    stop: F,
+) -> Vec<(usize, Wavefront)>
+where
+    T: Eq,
+    F: Fn(&(usize, Wavefront)) -> bool,
+{
+    let (xlen, ylen) = (xs.len(), ys.len());
+    let diags = xlen + ylen + 1;
+    let lo = if end_gaps.ys_start { 0 } else { ylen };
+    let hi = if end_gaps.xs_start { ylen + xlen } else { ylen };
+    let init: Wavefront = (lo..hi + 1)
+        .map(|k| {
+            let (i, j) = start_of(k, ylen);
+            Some((j + match_len(xs, i, ys, j), None))
+        })
+        .collect();
+    let mut dp: Vec<(usize, Wavefront)> = vec![(lo, init)];
+    while dp.len() <= max_dist && !stop(dp.last().unwrap()) {
+        let prev = dp.last().unwrap();
+        let (lo, hi) = (
+            prev.0.saturating_sub(1),
+            (prev.0 + prev.1.len()).min(diags - 1),
+        );
+        let new_d: Vec<_> = (lo..hi + 1)
+            .map(|k| {
+                let from_above = k.checked_sub(1).and_then(|k| get(prev, k)).map(|(j, _)| j);
+                let from_mat = get(prev, k).map(|(j, _)| j + 1);
+                let from_left = get(prev, k + 1).map(|(j, _)| j + 1);
+                // Discard the moves running off the sequences.
+                let from_above = from_above.filter(|&j| j + k - ylen <= xlen);
+                let from_mat = from_mat.filter(|&j| j <= ylen && j + k - ylen <= xlen);
+                let from_left = from_left.filter(|&j| j <= ylen);
+                if from_above.is_none() && from_mat.is_none() && from_left.is_none() {
+                    return None;
+                }
+                let (max_reach, trace) = max_three(from_above, from_mat, from_left);
+                let i = max_reach + k - ylen;
+                Some((max_reach + match_len(xs, i, ys, max_reach), trace))
+            })
+            .collect();
+        dp.push((lo, new_d));
+    }
+    dp
+}
+
+// The start of the diagonal k.
+fn start_of(k: usize, ylen: usize) -> (usize, usize) {
+    match ylen <= k {
+        true => (k - ylen, 0),
+        false => (0, ylen - k),
+    }
+}
+
+fn get((lo, wf): &(usize, Wavefront), k: usize) -> Option<(usize, Option<u8>)> {
+    k.checked_sub(*lo)
+        .and_then(|idx| wf.get(idx).copied())
+        .flatten()
+}
+
+// The diagonals of a wavefront reaching an end allowed by `end_gaps`, and their furthest reaching points.
+fn reached_ends(
+    (lo, wf): &(usize, Wavefront),
+    xlen: usize,
+    ylen: usize,
+    end_gaps: EndGaps,
+) -> impl Iterator<Item = (usize, usize)> + '_ {
+    wf.iter()
+        .enumerate()
+        .filter_map(move |(idx, cell)| cell.map(|(j, _)| (lo + idx, j)))
+        .filter(move |&(k, j)| end_gaps.is_end(j + k - ylen, j, xlen, ylen))
+}
+
+fn semi_global_traceback(
+    dp: &[(usize, Wavefront)],
+    mut dist: usize,
+    mut k: usize,
+    ylen: usize,
+) -> (Region, Alignment) {
+    let (mut ypos, mut prev) = get(&dp[dist], k).unwrap();
+    let (xend, yend) = (ypos + k - ylen, ypos);
+    let mut ops = RunLengthAlignment::default();
+    while let Some(trace) = prev {
+        let old_ypos = ypos;
+        let op = match trace {
+            0 => {
+                k -= 1;
+                Op::Del
+            }
+            1 => Op::Mismatch,
+            2 => {
+                k += 1;
+                Op::Ins
+            }
+            _ => panic!(),
+        };
+        dist -= 1;
+        (ypos, prev) = get(&dp[dist], k).unwrap();
+        let len = match op {
+            Op::Del => old_ypos - ypos,
+            _ => old_ypos - ypos - 1,
+        };
+        ops.push(Op::Match, len as u32);
+        ops.push(op, 1);
+    }
+    assert_eq!(dist, 0);
+    let (xstart, ystart) = start_of(k, ylen);
+    ops.push(Op::Match, (ypos - ystart) as u32);
+    ops.reverse();
+    let region = Region {
+        xstart,
+        xend,
+        ystart,
+        yend,
+    };
+    (region, ops.into())
+}
+
+fn match_len<T: Eq>(xs: &[T], x_start: usize, ys: &[T], y_start: usize) -> usize {
+    match_len_by(xs, x_start, ys, y_start, |x, y| x == y)
+}
+
+fn match_len_by<T, F>(xs: &[T], x_start: usize, ys: &[T], y_start: usize, eq: F) -> usize
+where
+    F: Fn(&T, &T) -> bool,
+{
     let xs = xs.iter().skip(x_start);
     let ys = ys.iter().skip(y_start);
-    std::iter::zip(xs, ys).take_while(|(x, y)| x == y).count()
+    std::iter::zip(xs, ys).take_while(|(x, y)| eq(x, y)).count()
 }
 
 fn max_three(x: Option<usize>, y: Option<usize>, z: Option<usize>) -> (usize, Option<u8>) {
@@ -152,4 +376,93 @@ mod tests {
             assert_eq!(dist_1, dist_2)
         }
     }
+    #[test]
+    fn bounded_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let expected = super::edit_dist(&xs, &ys);
+            for max_dist in 0..expected.0 + 2 {
+                let bounded = super::edit_dist_bounded(&xs, &ys, max_dist);
+                match max_dist < expected.0 {
+                    true => assert!(bounded.is_none()),
+                    false => assert_eq!(bounded.as_ref(), Some(&expected)),
+                }
+            }
+        }
+    }
+    #[test]
+    fn semi_global_random() {
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+            let seq2 = kiley::gen_seq::introduce_randomness(&seq, &mut rng, &prof);
+            let seq2 = &seq2[rng.gen_range(0..5)..];
+            let end_gaps = EndGaps {
+                xs_start: rng.gen_bool(0.5),
+                xs_end: rng.gen_bool(0.5),
+                ys_start: rng.gen_bool(0.5),
+                ys_end: rng.gen_bool(0.5),
+            };
+            use crate::edit_dist_usual::edit_dist_semi_global as usual;
+            let (dist_1, _, _) = usual(&seq, seq2, end_gaps);
+            let (dist_2, region, aln) = edit_dist_semi_global(&seq, seq2, end_gaps);
+            assert_eq!(dist_1, dist_2);
+            let xs = &seq[region.xstart..region.xend];
+            let ys = &seq2[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+            assert_eq!(aln.dist_and_num_of_gaps().0, dist_2);
+        }
+        let (dist, region, _) = edit_dist_semi_global(b"ACGT", b"ACGT", EndGaps::default());
+        assert_eq!((dist, region.xend, region.yend), (0, 4, 4));
+        let (dist, _, aln) = edit_dist_semi_global(b"", b"ACG", EndGaps::default());
+        assert_eq!((dist, aln.to_string().as_str()), (3, "III"));
+    }
+    #[test]
+    fn overlap_random() {
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let xs = &seq[rng.gen_range(0..15)..];
+            let ys = kiley::gen_seq::introduce_randomness(
+                &seq[..rng.gen_range(25..40)],
+                &mut rng,
+                &prof,
+            );
+            let overlap = match rng.gen_bool(0.5) {
+                true => Overlap::SuffixPrefix,
+                false => Overlap::PrefixSuffix,
+            };
+            let max_dist = rng.gen_range(0..5);
+            use crate::edit_dist_usual::edit_dist_overlap as usual;
+            // The overlap ends at the same point, while the start may differ by ties.
+            let end =
+                |&(dist, region, _): &(u32, Region, Alignment)| (dist, region.xend, region.yend);
+            let expected = usual(xs, &ys, overlap, max_dist);
+            let result = edit_dist_overlap(xs, &ys, overlap, max_dist);
+            assert_eq!(result.as_ref().map(end), expected.as_ref().map(end));
+            if let Some((dist, region, aln)) = result {
+                let (xs, ys) = (
+                    &xs[region.xstart..region.xend],
+                    &ys[region.ystart..region.yend],
+                );
+                assert_eq!(aln.validate(xs, ys), Ok(()));
+                assert_eq!(aln.dist_and_num_of_gaps().0, dist);
+            }
+        }
+        let xs = b"GGGGGGACGTACGT";
+        let ys = b"ACGTTCGTCCCCCCC";
+        let (dist, region, _) = edit_dist_overlap(xs, ys, Overlap::SuffixPrefix, 1).unwrap();
+        assert_eq!((dist, region.xstart, region.ylen()), (1, 6, 8));
+        assert!(edit_dist_overlap(b"AAAA", b"CCCC", Overlap::SuffixPrefix, 0).is_none());
+    }
 }
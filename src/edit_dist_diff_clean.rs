@@ -1,83 +1,418 @@
 use crate::alignments::*;
+use crate::match_policy::MatchPolicy;
+use crate::run_length::RunLengthAlignment;
 /// Usual edit distance alignments and its path.
-pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
+pub fn edit_dist<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    let (dist, aln) = edit_dist_rle(xs, ys);
+    (dist, aln.into())
+}
+
+/// Same as `edit_dist`, but the path is built as runs directly from the match snakes.
+pub fn edit_dist_rle<T: Eq>(xs: &[T], ys: &[T]) -> (u32, RunLengthAlignment) {
+    edit_dist_rle_bounded(xs, ys, xs.len() + ys.len() + 1, |x, y| x == y).unwrap()
+}
+
+/// Same as `edit_dist`, but the bases are compared by `policy`.
+pub fn edit_dist_with_policy(xs: &[u8], ys: &[u8], policy: MatchPolicy) -> (u32, Alignment) {
+    let (dist, aln) = edit_dist_rle_bounded(xs, ys, xs.len() + ys.len() + 1, |&x, &y| {
+        policy.matches(x, y)
+    })
+    .unwrap();
+    (dist, aln.into())
+}
+
+/// Same as `edit_dist`, but returns `None` as soon as the distance turns out to exceed `max_dist`.
+pub fn edit_dist_bounded<T: Eq>(xs: &[T], ys: &[T], max_dist: u32) -> Option<(u32, Alignment)> {
+    let (dist, aln) = edit_dist_rle_bounded(xs, ys, max_dist as usize, |x, y| x == y)?;
+    Some((dist, aln.into()))
+}
+
+fn edit_dist_rle_bounded<T, F>(
+    xs: &[T],
+    ys: &[T],
+    max_dist: usize,
+    eq: F,
+) -> Option<(u32, RunLengthAlignment)>
+where
+    T: Eq,
+    F: Fn(&T, &T) -> bool,
+{
     if xs == ys {
-        return (0, Alignment::new(vec![Op::Match; xs.len()]));
+        return Some((
+            0,
+            RunLengthAlignment::new(vec![(Op::Match, xs.len() as u32)]),
+        ));
+    }
+    if max_dist < xs.len().abs_diff(ys.len()) {
+        return None;
     }
     // h -> d = (the f.r.p of the d-h diagonal with edit distance h, the num of gaps, w. on indel, the tracing).
     // 32bits, 16bits, 8bits, 8bits.
-    let init_match = match_len(xs, 0, ys, 0);
-    let mut dp = vec![vec![ReachPoint::new(init_match)]];
-    for dist in 1..xs.len() + ys.len() + 2 {
-        let new_d: Vec<_> = (0..2 * dist + 1)
-            .map(|diag| {
-                let from_above = dp[dist - 1].get(diag).map(|x| x.from_above());
-                let from_mat = match diag < 1 {
-                    true => None,
-                    false => dp[dist - 1].get(diag - 1).map(|x| x.from_mat()),
-                };
-                let from_left = match diag < 2 {
-                    true => None,
-                    false => dp[dist - 1].get(diag - 2).map(|x| x.from_left()),
+    let init_match = match_len_by(xs, 0, ys, 0, &eq);
+    let mut dp = vec![vec![Some(ReachPoint::new(init_match))]];
+    let end_diag = loop {
+        let dist = dp.len() - 1;
+        if let Some(diag) = end_diag(dp.last().unwrap(), dist, xs.len(), ys.len()) {
+            break diag;
+        }
+        if max_dist < dist + 1 {
+            return None;
+        }
+        dp.push(next_wavefront(dp.last().unwrap(), dist + 1, xs, ys, &eq));
+    };
+    let opt_dist = dp.len() - 1;
+    let mut ops = RunLengthAlignment::default();
+    assert_eq!(trace_layers(&dp, end_diag, &mut ops), 0);
+    ops.push(Op::Match, init_match as u32);
+    ops.reverse();
+    Some((opt_dist as u32, ops))
+}
+
+/// Same as `edit_dist`, but the wavefronts take O(s) memory by the bidirectional wavefront algorithm (BiWFA),
+/// where s is the edit distance.
+/// The forward and the reverse wavefronts are extended in turn until they overlap,
+/// and the two halves split at the overlap are aligned recursively.
+/// The distance is the same as `edit_dist`. The subproblems closer than `BASE_DIST` are aligned by `edit_dist_rle`
+/// with its gap-count tie-breaking, and the breakpoint is the overlap with the fewest gaps in total.
+/// As the split fixes a point the alignment goes through, the number of gaps may differ from `edit_dist`
+/// when the distance is `BASE_DIST` or more, either way, as neither is minimal among the optimal alignments.
+pub fn edit_dist_linear<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    let mut ops = RunLengthAlignment::default();
+    let dist = bidirectional(xs, ys, &mut ops);
+    (dist, ops.into())
+}
+
+// Below this distance, the subproblem is aligned by `edit_dist_rle`.
+const BASE_DIST: usize = 16;
+
+fn bidirectional<T: Eq>(xs: &[T], ys: &[T], ops: &mut RunLengthAlignment) -> u32 {
+    let (rev_xs, rev_ys): (Vec<&T>, Vec<&T>) =
+        (xs.iter().rev().collect(), ys.iter().rev().collect());
+    let (eq, rev_eq) = (|x: &T, y: &T| x == y, |x: &&T, y: &&T| x == y);
+    let init = |len: usize| vec![Some(ReachPoint::new(len))];
+    let (mut fwd, mut bwd) = (
+        init(match_len(xs, 0, ys, 0)),
+        init(match_len(&rev_xs, 0, &rev_ys, 0)),
+    );
+    let (mut fwd_dist, mut bwd_dist) = (0, 0);
+    let (i, j) = loop {
+        if let Some(breakpoint) = meet(&fwd, fwd_dist, &bwd, bwd_dist, xs.len(), ys.len()) {
+            break breakpoint;
+        }
+        if bwd_dist < fwd_dist {
+            bwd_dist += 1;
+            bwd = next_wavefront(&bwd, bwd_dist, &rev_xs, &rev_ys, &rev_eq);
+        } else {
+            fwd_dist += 1;
+            fwd = next_wavefront(&fwd, fwd_dist, xs, ys, &eq);
+        }
+    };
+    if fwd_dist + bwd_dist < BASE_DIST {
+        let (dist, aln) = edit_dist_rle(xs, ys);
+        for &(op, len) in aln.runs() {
+            ops.push(op, len);
+        }
+        return dist;
+    }
+    // Release the wavefronts before recursing, so that only one level holds them.
+    drop((fwd, bwd, rev_xs, rev_ys));
+    bidirectional(&xs[..i], &ys[..j], ops) + bidirectional(&xs[i..], &ys[j..], ops)
+}
+
+// The furthest forward point on a diagonal where the two wavefronts overlap, with the fewest gaps in total.
+// A gap running through the point is counted once.
+fn meet(
+    fwd: &[Option<ReachPoint>],
+    fwd_dist: usize,
+    bwd: &[Option<ReachPoint>],
+    bwd_dist: usize,
+    xlen: usize,
+    ylen: usize,
+) -> Option<(usize, usize)> {
+    fwd.iter()
+        .enumerate()
+        .filter_map(|(diag, f)| f.map(|f| (diag, f)))
+        .filter_map(|(diag, f)| {
+            // The diagonal j - i = k is j' - i' = ylen - xlen - k on the reversed sequences.
+            let bwd_diag = (ylen + fwd_dist + bwd_dist).checked_sub(xlen + diag)?;
+            let b = bwd.get(bwd_diag).copied().flatten()?;
+            let j = f.position();
+            if j + b.position() < ylen {
+                return None;
+            }
+            // Both ends sit on the same gap only if the two points coincide without a snake in between.
+            let joined = j + b.position() == ylen && f.gap() != 0 && f.gap() == b.gap();
+            let gaps = f.num_gaps() + b.num_gaps() - joined as u32;
+            Some(((j + fwd_dist - diag, j), gaps))
+        })
+        .min_by_key(|x| x.1)
+        .map(|x| x.0)
+}
+
+// Trace back from the `diag`-th point of the last wavefront to the first one, pushing the operations in reverse.
+// Returns the diagonal reached in the first wavefront.
+fn trace_layers(
+    wavefronts: &[Vec<Option<ReachPoint>>],
+    mut diag: usize,
+    ops: &mut RunLengthAlignment,
+) -> usize {
+    for dist in (1..wavefronts.len()).rev() {
+        let point = wavefronts[dist][diag].unwrap();
+        let trace = point.trace().unwrap();
+        match trace {
+            Op::Del => {}
+            Op::Mismatch => diag -= 1,
+            Op::Ins => diag -= 2,
+            _ => panic!(),
+        }
+        let prev = wavefronts[dist - 1][diag].unwrap().position();
+        let len = match trace {
+            Op::Del => point.position() - prev,
+            _ => point.position() - prev - 1,
+        };
+        ops.push(Op::Match, len as u32);
+        ops.push(trace, 1);
+    }
+    diag
+}
+
+// The diagonal of the wavefront of distance `dist` reaching the end of the DP matrix, if any.
+fn end_diag(
+    wavefront: &[Option<ReachPoint>],
+    dist: usize,
+    xlen: usize,
+    ylen: usize,
+) -> Option<usize> {
+    wavefront.iter().enumerate().position(|(diag, point)| {
+        point.is_some_and(|p| p.position() == ylen && p.position() + dist - diag == xlen)
+    })
+}
+
+// The wavefront of distance `dist`, indexed by diag = j - i + dist.
+fn next_wavefront<T, F>(
+    prev: &[Option<ReachPoint>],
+    dist: usize,
+    xs: &[T],
+    ys: &[T],
+    eq: &F,
+) -> Vec<Option<ReachPoint>>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    (0..2 * dist + 1)
+        .map(|diag| {
+            let from_above = prev.get(diag).and_then(|&x| x).map(|x| x.from_above());
+            let from_mat = diag
+                .checked_sub(1)
+                .and_then(|d| prev.get(d))
+                .and_then(|&x| x);
+            let from_mat = from_mat.map(|x| x.from_mat());
+            let from_left = diag
+                .checked_sub(2)
+                .and_then(|d| prev.get(d))
+                .and_then(|&x| x);
+            let from_left = from_left.map(|x| x.from_left());
+            // Discard the moves running off the sequences.
+            let in_range = |x: &ReachPoint| {
+                let j = x.position();
+                j <= ys.len() && diag <= j + dist && j + dist - diag <= xs.len()
+            };
+            let from_above = from_above.filter(in_range);
+            let from_mat = from_mat.filter(in_range);
+            let from_left = from_left.filter(in_range);
+            if from_above.is_none() && from_mat.is_none() && from_left.is_none() {
+                return None;
+            }
+            let max_reach = max_of_three(from_above, from_mat, from_left);
+            let j = max_reach.position();
+            Some(max_reach.add(match_len_by(xs, j + dist - diag, ys, j, eq)))
+        })
+        .collect()
+}
+
+/// Semi-global edit distance, minimizing the number of gaps among the optimal alignments.
+/// The end gaps flagged in `end_gaps` are free.
+/// Returns the distance, the aligned region, and the alignment of the region only.
+pub fn edit_dist_semi_global<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+) -> (u32, Region, Alignment) {
+    let (xlen, ylen) = (xs.len(), ys.len());
+    // The reached end with the fewest gaps.
+    let find_end = |wf: &SemiGlobalWavefront| {
+        reached_ends(wf, xlen, ylen, end_gaps)
+            .min_by_key(|(_, cell)| cell.num_gaps())
+            .map(|(k, _)| k)
+    };
+    let dp = semi_global_wavefronts(xs, ys, end_gaps, usize::MAX, |wf| find_end(wf).is_some());
+    let k = find_end(dp.last().unwrap()).unwrap();
+    let dist = dp.len() - 1;
+    let (region, aln) = semi_global_traceback(&dp, dist, k, ylen);
+    (dist as u32, region, aln)
+}
+
+/// Same as `edit_dist_usual_clean::edit_dist_overlap`, the longest overlap with at most `max_dist` edits.
+/// Returns the distance, the overlapping region, and its alignment, or `None` if they do not overlap.
+pub fn edit_dist_overlap<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    overlap: Overlap,
+    max_dist: u32,
+) -> Option<(u32, Region, Alignment)> {
+    let (xlen, ylen) = (xs.len(), ys.len());
+    let end_gaps = overlap.end_gaps();
+    let max_dist = (max_dist as usize).min(xlen + ylen);
+    let dp = semi_global_wavefronts(xs, ys, end_gaps, max_dist, |_| false);
+    let overlap_len = |k: usize, j: usize| match overlap {
+        Overlap::SuffixPrefix => j,
+        Overlap::PrefixSuffix => j + k - ylen,
+    };
+    // The longest overlap, at the distance it is reached first.
+    let (_, dist, k) = dp
+        .iter()
+        .enumerate()
+        .flat_map(|(dist, wf)| {
+            reached_ends(wf, xlen, ylen, end_gaps)
+                .map(move |(k, cell)| (overlap_len(k, cell.position()), dist, k))
+        })
+        .filter(|&(len, _, _)| 0 < len)
+        .min_by_key(|&(len, dist, _)| (std::cmp::Reverse(len), dist))?;
+    let (region, aln) = semi_global_traceback(&dp, dist, k, ylen);
+    Some((dist as u32, region, aln))
+}
+
+// The first diagonal of a wavefront of a semi-global alignment, and the furthest reaching points from it.
+type SemiGlobalWavefront = (usize, Vec<Option<ReachPoint>>);
+
+// The wavefronts of a semi-global alignment.
+// As free start gaps put many diagonals on the wavefront of distance zero,
+// the wavefronts are indexed by k = i - j + ys.len() rather than by h.
+// The wavefront of distance zero covers the free start diagonals, and it grows by one diagonal per distance.
+// Stops at the first wavefront satisfying `stop`, or at the wavefront of distance `max_dist`.
+fn semi_global_wavefronts<T, F>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+    max_dist: usize,
+    stop: F,
+) -> Vec<SemiGlobalWavefront>
+where
+    T: Eq,
+    F: Fn(&SemiGlobalWavefront) -> bool,
+{
+    let (xlen, ylen) = (xs.len(), ys.len());
+    let diags = xlen + ylen + 1;
+    let lo = if end_gaps.ys_start { 0 } else { ylen };
+    let hi = if end_gaps.xs_start { ylen + xlen } else { ylen };
+    let init: Vec<_> = (lo..hi + 1)
+        .map(|k| {
+            let (i, j) = start_of(k, ylen);
+            Some(ReachPoint::new(j).add(match_len(xs, i, ys, j)))
+        })
+        .collect();
+    let mut dp = vec![(lo, init)];
+    while dp.len() <= max_dist && !stop(dp.last().unwrap()) {
+        let prev = dp.last().unwrap();
+        let (lo, hi) = (
+            prev.0.saturating_sub(1),
+            (prev.0 + prev.1.len()).min(diags - 1),
+        );
+        let new_d: Vec<_> = (lo..hi + 1)
+            .map(|k| {
+                let from_above = k.checked_sub(1).and_then(|k| get(prev, k));
+                let from_above = from_above.map(|x| x.from_above());
+                let from_mat = get(prev, k).map(|x| x.from_mat());
+                let from_left = get(prev, k + 1).map(|x| x.from_left());
+                // Discard the moves running off the sequences.
+                let in_range = |x: &ReachPoint| {
+                    let j = x.position();
+                    j <= ylen && j + k - ylen <= xlen
                 };
+                let from_above = from_above.filter(in_range);
+                let from_mat = from_mat.filter(in_range);
+                let from_left = from_left.filter(in_range);
+                if from_above.is_none() && from_mat.is_none() && from_left.is_none() {
+                    return None;
+                }
                 let max_reach = max_of_three(from_above, from_mat, from_left);
-                let reached_pos = max_reach.position();
-                let i = (reached_pos + dist) - diag;
-                let snake_len = match_len(xs, i, ys, reached_pos);
-                max_reach.add(snake_len)
+                let j = max_reach.position();
+                Some(max_reach.add(match_len(xs, j + k - ylen, ys, j)))
             })
             .collect();
-        let reached = new_d.iter().enumerate().any(|(diag, pos)| {
-            let i = (pos.position() + dist) - diag;
-            i == xs.len() && pos.position() == ys.len()
-        });
-        dp.push(new_d);
-        if reached {
-            break;
-        }
-        assert!(dist < xs.len() + ys.len() + 1);
+        dp.push((lo, new_d));
     }
-    let opt_dist = dp.len() - 1;
-    let (mut diag, opt_cell) = dp
-        .last()
-        .unwrap()
-        .iter()
+    dp
+}
+
+// The start of the diagonal k.
+fn start_of(k: usize, ylen: usize) -> (usize, usize) {
+    match ylen <= k {
+        true => (k - ylen, 0),
+        false => (0, ylen - k),
+    }
+}
+
+fn get((lo, wf): &SemiGlobalWavefront, k: usize) -> Option<ReachPoint> {
+    k.checked_sub(*lo)
+        .and_then(|idx| wf.get(idx).copied())
+        .flatten()
+}
+
+// The diagonals of a wavefront reaching an end allowed by `end_gaps`, and their furthest reaching points.
+fn reached_ends(
+    (lo, wf): &SemiGlobalWavefront,
+    xlen: usize,
+    ylen: usize,
+    end_gaps: EndGaps,
+) -> impl Iterator<Item = (usize, ReachPoint)> + '_ {
+    wf.iter()
         .enumerate()
-        .find(|(diag, pos)| {
-            let i = (pos.position() + opt_dist) - diag;
-            i == xs.len() && pos.position() == ys.len()
+        .filter_map(move |(idx, cell)| cell.map(|cell| (lo + idx, cell)))
+        .filter(move |&(k, cell)| {
+            let j = cell.position();
+            end_gaps.is_end(j + k - ylen, j, xlen, ylen)
         })
-        .unwrap();
-    let mut prev = *opt_cell;
-    let mut ops = vec![];
-    let mut dist = opt_dist;
+}
+
+fn semi_global_traceback(
+    dp: &[SemiGlobalWavefront],
+    mut dist: usize,
+    mut k: usize,
+    ylen: usize,
+) -> (Region, Alignment) {
+    let mut prev = get(&dp[dist], k).unwrap();
+    let (xend, yend) = (prev.position() + k - ylen, prev.position());
+    let mut ops = RunLengthAlignment::default();
     while let Some(trace) = prev.trace() {
         let old_ypos = prev.position();
         match trace {
-            Op::Del => {}
-            Op::Mismatch => diag -= 1,
-            Op::Ins => diag -= 2,
+            Op::Del => k -= 1,
+            Op::Mismatch => {}
+            Op::Ins => k += 1,
             _ => panic!(),
         }
         dist -= 1;
-        prev = dp[dist][diag];
+        prev = get(&dp[dist], k).unwrap();
         let new_ypos = prev.position();
         let len = match trace {
             Op::Del => old_ypos - new_ypos,
-            Op::Mismatch | Op::Ins => old_ypos - new_ypos - 1,
-            _ => panic!(),
+            _ => old_ypos - new_ypos - 1,
         };
-        ops.extend(std::iter::repeat(Op::Match).take(len));
-        ops.push(trace);
+        ops.push(Op::Match, len as u32);
+        ops.push(trace, 1);
     }
     assert_eq!(dist, 0);
-    let ypos = prev.position();
-    let xpos = (ypos + dist) - diag;
-    assert_eq!(xpos, ypos);
-    ops.extend(std::iter::repeat(Op::Match).take(xpos));
+    let (xstart, ystart) = start_of(k, ylen);
+    ops.push(Op::Match, (prev.position() - ystart) as u32);
     ops.reverse();
-    let aln = Alignment::new(ops);
-    (opt_dist as u32, aln)
+    let region = Region {
+        xstart,
+        xend,
+        ystart,
+        yend,
+    };
+    (region, ops.into())
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -105,6 +440,13 @@ impl ReachPoint {
         }
         Self(bits)
     }
+    // DEL or INS if the point is on a gap, or zero otherwise.
+    fn gap(&self) -> u64 {
+        (self.0 & ON_GAP_FLAG) >> 8
+    }
+    fn num_gaps(&self) -> u32 {
+        ((self.0 & NUM_GAP) >> 16) as u32
+    }
     fn position(&self) -> usize {
         let &Self(bits) = self;
         (bits >> 32) as usize
@@ -167,10 +509,17 @@ fn max_of_three(x: Option<ReachPoint>, y: Option<ReachPoint>, z: Option<ReachPoi
     }
 }
 
-fn match_len(xs: &[u8], x_start: usize, ys: &[u8], y_start: usize) -> usize {
+fn match_len<T: Eq>(xs: &[T], x_start: usize, ys: &[T], y_start: usize) -> usize {
+    match_len_by(xs, x_start, ys, y_start, |x, y| x == y)
+}
+
+fn match_len_by<T, F>(xs: &[T], x_start: usize, ys: &[T], y_start: usize, eq: F) -> usize
+where
+    F: Fn(&T, &T) -> bool,
+{
     let xs = xs.iter().skip(x_start);
     let ys = ys.iter().skip(y_start);
-    std::iter::zip(xs, ys).take_while(|(x, y)| x == y).count()
+    std::iter::zip(xs, ys).take_while(|(x, y)| eq(x, y)).count()
 }
 
 #[cfg(test)]
@@ -200,4 +549,109 @@ mod tests {
             assert_eq!(dist_1, dist_2)
         }
     }
+    #[test]
+    fn linear_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.1, 0.05, 0.05);
+        for len in [0, 1, 10, 100, 500] {
+            for _ in 0..20 {
+                let xs = kiley::gen_seq::generate_seq(&mut rng, len);
+                let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+                let (dist, aln) = super::edit_dist(&xs, &ys);
+                let (linear, linear_aln) = edit_dist_linear(&xs, &ys);
+                assert_eq!(dist, linear);
+                assert_eq!(linear_aln.validate(&xs, &ys), Ok(()));
+                assert_eq!(linear_aln.dist_and_num_of_gaps().0, dist);
+                // Below `BASE_DIST`, it is `edit_dist` itself.
+                if (dist as usize) < BASE_DIST {
+                    assert_eq!(linear_aln, aln);
+                }
+            }
+        }
+    }
+    #[test]
+    fn bounded_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let expected = super::edit_dist(&xs, &ys);
+            for max_dist in 0..expected.0 + 2 {
+                let bounded = super::edit_dist_bounded(&xs, &ys, max_dist);
+                match max_dist < expected.0 {
+                    true => assert!(bounded.is_none()),
+                    false => assert_eq!(bounded.as_ref(), Some(&expected)),
+                }
+            }
+        }
+    }
+    #[test]
+    fn semi_global_random() {
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+            let seq2 = kiley::gen_seq::introduce_randomness(&seq, &mut rng, &prof);
+            let seq2 = &seq2[rng.gen_range(0..5)..];
+            let end_gaps = EndGaps {
+                xs_start: rng.gen_bool(0.5),
+                xs_end: rng.gen_bool(0.5),
+                ys_start: rng.gen_bool(0.5),
+                ys_end: rng.gen_bool(0.5),
+            };
+            use crate::edit_dist_usual_clean::edit_dist_semi_global as usual;
+            let (dist_1, _, _) = usual(&seq, seq2, end_gaps);
+            let (dist_2, region, aln) = edit_dist_semi_global(&seq, seq2, end_gaps);
+            assert_eq!(dist_1, dist_2);
+            let xs = &seq[region.xstart..region.xend];
+            let ys = &seq2[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+            assert_eq!(aln.dist_and_num_of_gaps().0, dist_2);
+            if end_gaps == EndGaps::default() {
+                let (_, aln_1) = super::edit_dist(&seq, seq2);
+                assert_eq!(aln.dist_and_num_of_gaps(), aln_1.dist_and_num_of_gaps());
+            }
+        }
+    }
+    #[test]
+    fn overlap_random() {
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let xs = &seq[rng.gen_range(0..15)..];
+            let ys = kiley::gen_seq::introduce_randomness(
+                &seq[..rng.gen_range(25..40)],
+                &mut rng,
+                &prof,
+            );
+            let overlap = match rng.gen_bool(0.5) {
+                true => Overlap::SuffixPrefix,
+                false => Overlap::PrefixSuffix,
+            };
+            let max_dist = rng.gen_range(0..5);
+            use crate::edit_dist_usual_clean::edit_dist_overlap as usual;
+            // The overlap ends at the same point, while the start may differ by ties.
+            let end =
+                |&(dist, region, _): &(u32, Region, Alignment)| (dist, region.xend, region.yend);
+            let expected = usual(xs, &ys, overlap, max_dist);
+            let result = edit_dist_overlap(xs, &ys, overlap, max_dist);
+            assert_eq!(result.as_ref().map(end), expected.as_ref().map(end));
+            if let Some((dist, region, aln)) = result {
+                let (xs, ys) = (
+                    &xs[region.xstart..region.xend],
+                    &ys[region.ystart..region.yend],
+                );
+                assert_eq!(aln.validate(xs, ys), Ok(()));
+                assert_eq!(aln.dist_and_num_of_gaps().0, dist);
+            }
+        }
+    }
 }
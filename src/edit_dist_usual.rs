@@ -1,38 +1,219 @@
 use super::alignments::*;
+use crate::match_policy::MatchPolicy;
 /// Usual edit distance alignments and its path.
-pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
-    let mut dp = vec![vec![(0, None); ys.len() + 1]; xs.len() + 1];
-    for i in 1..xs.len() + 1 {
-        dp[i][0] = (i as u32, Some(Op::Del));
+pub fn edit_dist<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    edit_dist_by(xs, ys, |x, y| x == y)
+}
+
+/// Same as `edit_dist`, but the bases are compared by `policy`.
+pub fn edit_dist_with_policy(xs: &[u8], ys: &[u8], policy: MatchPolicy) -> (u32, Alignment) {
+    edit_dist_by(xs, ys, |&x, &y| policy.matches(x, y))
+}
+
+fn edit_dist_by<T, F>(xs: &[T], ys: &[T], eq: F) -> (u32, Alignment)
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let dp = fill(xs, ys, eq, EndGaps::default(), full_band(xs, ys), u32::MAX).unwrap();
+    let (dist, _, aln) = traceback(&dp, xs.len(), ys.len());
+    (dist, aln)
+}
+
+/// Edit distance alignment in linear memory by Hirschberg's divide-and-conquer.
+/// Returns the same distance as `edit_dist`, with an optimal alignment that may differ from it.
+pub fn edit_dist_linear<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    let mut ops = Vec::with_capacity(xs.len() + ys.len());
+    hirschberg(xs, ys, &mut ops);
+    let aln = Alignment::new(ops);
+    let dist = aln.ops.iter().filter(|&&op| op != Op::Match).count() as u32;
+    (dist, aln)
+}
+
+fn hirschberg<T: Eq>(xs: &[T], ys: &[T], ops: &mut Vec<Op>) {
+    if xs.len() <= 1 || ys.is_empty() {
+        // The DP matrix has at most two rows.
+        ops.extend(edit_dist(xs, ys).1.ops);
+        return;
     }
-    for j in 1..ys.len() + 1 {
-        dp[0][j] = (j as u32, Some(Op::Ins));
+    let mid = xs.len() / 2;
+    let fwd = last_row(xs[..mid].iter(), ys.iter());
+    let rev = last_row(xs[mid..].iter().rev(), ys.iter().rev());
+    let (j, _) = fwd
+        .iter()
+        .zip(rev.iter().rev())
+        .map(|(f, r)| f + r)
+        .enumerate()
+        .min_by_key(|x| x.1)
+        .unwrap();
+    hirschberg(&xs[..mid], &ys[..j], ops);
+    hirschberg(&xs[mid..], &ys[j..], ops);
+}
+
+// The last row of the DP matrix.
+fn last_row<'a, T, I, J>(xs: I, ys: J) -> Vec<u32>
+where
+    T: Eq + 'a,
+    I: Iterator<Item = &'a T>,
+    J: Iterator<Item = &'a T> + Clone,
+{
+    let mut row: Vec<u32> = (0..).take(ys.clone().count() + 1).collect();
+    for (i, x) in xs.enumerate().map(|(i, x)| (i as u32 + 1, x)) {
+        let mut diag = row[0];
+        row[0] = i;
+        for (j, y) in ys.clone().enumerate().map(|(j, y)| (j + 1, y)) {
+            let mat_score = diag + (x != y) as u32;
+            diag = row[j];
+            row[j] = mat_score.min(row[j - 1] + 1).min(row[j] + 1);
+        }
+    }
+    row
+}
+
+/// Same as `edit_dist`, but returns `None` if the distance exceeds `max_dist`.
+/// Only the cells within the diagonals an alignment of distance `max_dist` can pass through are computed,
+/// and it returns `None` as soon as every cell of a row exceeds `max_dist`.
+pub fn edit_dist_bounded<T: Eq>(xs: &[T], ys: &[T], max_dist: u32) -> Option<(u32, Alignment)> {
+    let len_diff = ys.len() as isize - xs.len() as isize;
+    let band = (max_dist as usize).checked_sub(len_diff.unsigned_abs())? / 2;
+    let band = band.min(xs.len().max(ys.len())) as isize;
+    let diags = (len_diff.min(0) - band, len_diff.max(0) + band);
+    let dp = fill(xs, ys, |x, y| x == y, EndGaps::default(), diags, max_dist)?;
+    let (dist, _, aln) = traceback(&dp, xs.len(), ys.len());
+    (dist <= max_dist).then_some((dist, aln))
+}
+
+/// Semi-global edit distance. The end gaps flagged in `end_gaps` are free.
+/// Returns the distance, the aligned region, and the alignment of the region only.
+pub fn edit_dist_semi_global<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+) -> (u32, Region, Alignment) {
+    let dp = fill_semi_global(xs, ys, end_gaps);
+    let last_row = (0..ys.len() + 1).map(|j| (xs.len(), j));
+    let last_col = (0..xs.len() + 1).map(|i| (i, ys.len()));
+    let (xend, yend) = last_row
+        .chain(last_col)
+        .filter(|&(i, j)| end_gaps.is_end(i, j, xs.len(), ys.len()))
+        .min_by_key(|&(i, j)| dp.get(i, j).0)
+        .unwrap();
+    traceback(&dp, xend, yend)
+}
+
+/// The longest overlap of xs and ys in the given orientation with at most `max_dist` edits.
+/// The length is measured on the sequence contributing its prefix.
+/// Returns the distance, the overlapping region, and its alignment, or `None` if they do not overlap.
+pub fn edit_dist_overlap<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    overlap: Overlap,
+    max_dist: u32,
+) -> Option<(u32, Region, Alignment)> {
+    let dp = fill_semi_global(xs, ys, overlap.end_gaps());
+    let (xend, yend) = match overlap {
+        Overlap::SuffixPrefix => (1..ys.len() + 1)
+            .rev()
+            .map(|j| (xs.len(), j))
+            .find(|&(i, j)| dp.get(i, j).0 <= max_dist)?,
+        Overlap::PrefixSuffix => (1..xs.len() + 1)
+            .rev()
+            .map(|i| (i, ys.len()))
+            .find(|&(i, j)| dp.get(i, j).0 <= max_dist)?,
+    };
+    Some(traceback(&dp, xend, yend))
+}
+
+fn fill_semi_global<T: Eq>(xs: &[T], ys: &[T], end_gaps: EndGaps) -> Dp {
+    fill(xs, ys, |x, y| x == y, end_gaps, full_band(xs, ys), u32::MAX).unwrap()
+}
+
+// The distance and the last operation of a cell.
+type Cell = (u32, Option<Op>);
+
+// The DP matrix restricted to a band of diagonals.
+// rows[i] is the first column in the band and the cells (i, j) from it.
+struct Dp {
+    rows: Vec<(usize, Vec<Cell>)>,
+}
+
+// The cells outside of the band.
+const OUTSIDE: Cell = (u32::MAX / 2, None);
+
+impl Dp {
+    fn get(&self, i: usize, j: usize) -> Cell {
+        let (start, row) = &self.rows[i];
+        j.checked_sub(*start)
+            .and_then(|j| row.get(j))
+            .copied()
+            .unwrap_or(OUTSIDE)
     }
-    for (i, x) in xs.iter().enumerate().map(|(i, &x)| (i + 1, x)) {
-        for (j, y) in ys.iter().enumerate().map(|(j, &y)| (j + 1, y)) {
-            let mat_score = dp[i - 1][j - 1].0 + (x != y) as u32;
-            let ins_score = dp[i][j - 1].0 + 1;
-            let del_score = dp[i - 1][j].0 + 1;
-            let min = mat_score.min(ins_score).min(del_score);
-            dp[i][j] = if mat_score == min {
-                if x == y {
-                    (min, Some(Op::Match))
-                } else {
-                    (min, Some(Op::Mismatch))
+}
+
+// The diagonals j - i covering the whole DP matrix.
+fn full_band<T>(xs: &[T], ys: &[T]) -> (isize, isize) {
+    (-(xs.len() as isize), ys.len() as isize)
+}
+
+// Fills the cells (i, j) with lo <= j - i <= hi. The start gaps flagged in `end_gaps` are free.
+// Returns `None` as soon as every cell of a row exceeds `max_dist`.
+fn fill<T, F>(
+    xs: &[T],
+    ys: &[T],
+    eq: F,
+    end_gaps: EndGaps,
+    (lo, hi): (isize, isize),
+    max_dist: u32,
+) -> Option<Dp>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let mut dp = Dp {
+        rows: Vec::with_capacity(xs.len() + 1),
+    };
+    for i in 0..xs.len() + 1 {
+        let start = (i as isize + lo).max(0) as usize;
+        let end = (i as isize + hi).min(ys.len() as isize) as usize;
+        let mut row: Vec<Cell> = Vec::with_capacity(end + 1 - start);
+        for j in start..end + 1 {
+            let cell = match (i, j) {
+                (0, 0) => (0, None),
+                (0, _) if end_gaps.ys_start => (0, None),
+                (0, _) => (j as u32, Some(Op::Ins)),
+                (_, 0) if end_gaps.xs_start => (0, None),
+                (_, 0) => (i as u32, Some(Op::Del)),
+                _ => {
+                    let is_match = eq(&xs[i - 1], &ys[j - 1]);
+                    let mat_score = dp.get(i - 1, j - 1).0 + !is_match as u32;
+                    let ins_score = row.last().unwrap_or(&OUTSIDE).0 + 1;
+                    let del_score = dp.get(i - 1, j).0 + 1;
+                    let min = mat_score.min(ins_score).min(del_score);
+                    if mat_score == min {
+                        if is_match {
+                            (min, Some(Op::Match))
+                        } else {
+                            (min, Some(Op::Mismatch))
+                        }
+                    } else if ins_score == min {
+                        (min, Some(Op::Ins))
+                    } else {
+                        (min, Some(Op::Del))
+                    }
                 }
-            } else if ins_score == min {
-                (min, Some(Op::Ins))
-            } else if del_score == min {
-                (min, Some(Op::Del))
-            } else {
-                unreachable!()
             };
+            row.push(cell);
         }
+        if row.iter().all(|&(score, _)| max_dist < score) {
+            return None;
+        }
+        dp.rows.push((start, row));
     }
-    assert_eq!(dp[0][0], (0, None));
-    let (dist, mut operation) = dp[xs.len()][ys.len()];
+    Some(dp)
+}
+
+fn traceback(dp: &Dp, xend: usize, yend: usize) -> (u32, Region, Alignment) {
+    let (dist, mut operation) = dp.get(xend, yend);
     let mut aln = vec![];
-    let (mut xpos, mut ypos) = (xs.len(), ys.len());
+    let (mut xpos, mut ypos) = (xend, yend);
     while let Some(op) = operation {
         aln.push(op);
         match op {
@@ -43,11 +224,16 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
             Op::Ins => ypos -= 1,
             Op::Del => xpos -= 1,
         }
-        operation = dp[xpos][ypos].1;
+        operation = dp.get(xpos, ypos).1;
     }
     aln.reverse();
-    let aln = Alignment::new(aln);
-    (dist, aln)
+    let region = Region {
+        xstart: xpos,
+        xend,
+        ystart: ypos,
+        yend,
+    };
+    (dist, region, Alignment::new(aln))
 }
 
 #[cfg(test)]
@@ -79,4 +265,94 @@ mod tests {
             vec![Op::Match, Op::Match, Op::Mismatch, Op::Match, Op::Ins]
         );
     }
+    #[test]
+    fn linear_random() {
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.1, 0.05, 0.05);
+        for len in [0, 1, 2, 10, 100] {
+            for _ in 0..30 {
+                let xs = kiley::gen_seq::generate_seq(&mut rng, len);
+                let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+                let (dist, _) = edit_dist(&xs, &ys);
+                let (linear, aln) = edit_dist_linear(&xs, &ys);
+                assert_eq!(dist, linear);
+                assert_eq!(aln.validate(&xs, &ys), Ok(()));
+                let (linear, aln) = edit_dist_linear(&ys, &xs);
+                assert_eq!(dist, linear);
+                assert_eq!(aln.validate(&ys, &xs), Ok(()));
+            }
+        }
+    }
+    #[test]
+    fn bounded_random() {
+        use rand::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let expected = super::edit_dist(&xs, &ys);
+            for max_dist in 0..expected.0 + 2 {
+                let bounded = super::edit_dist_bounded(&xs, &ys, max_dist);
+                match max_dist < expected.0 {
+                    true => assert!(bounded.is_none()),
+                    false => assert_eq!(bounded.as_ref(), Some(&expected)),
+                }
+            }
+            let unbounded = super::edit_dist_bounded(&xs, &ys, u32::MAX);
+            assert_eq!(unbounded, Some(expected));
+        }
+    }
+    #[test]
+    fn semi_global() {
+        let xs = b"TTTTACGTACGTTTT";
+        let ys = b"ACGTTCG";
+        let (dist, region, aln) = edit_dist_semi_global(xs, ys, EndGaps::free_reference());
+        assert_eq!(dist, 1);
+        assert_eq!((region.xstart, region.xend), (4, 11));
+        assert_eq!((region.ystart, region.yend), (0, 7));
+        assert_eq!(aln.to_string(), "====X==");
+        let (dist, region, aln) = edit_dist_semi_global(ys, xs, EndGaps::default());
+        assert_eq!(dist, edit_dist(ys, xs).0);
+        assert_eq!((region.xend, region.yend), (ys.len(), xs.len()));
+        assert_eq!(aln.ops.len(), edit_dist(ys, xs).1.ops.len());
+        let end_gaps = EndGaps {
+            ys_start: true,
+            ys_end: true,
+            ..EndGaps::default()
+        };
+        let (dist, region, _) = edit_dist_semi_global(ys, xs, end_gaps);
+        assert_eq!(dist, 1);
+        assert_eq!((region.ystart, region.yend), (4, 11));
+        let end_gaps = EndGaps {
+            xs_end: true,
+            ..EndGaps::default()
+        };
+        let (dist, region, aln) = edit_dist_semi_global(b"ACGTAAAA", b"ACCT", end_gaps);
+        assert_eq!(dist, 1);
+        assert_eq!(region.xend, 4);
+        assert_eq!(aln.to_string(), "==X=");
+    }
+    #[test]
+    fn overlap() {
+        let xs = b"GGGGGGACGTACGT";
+        let ys = b"ACGTTCGTCCCCCCC";
+        let (dist, region, aln) = edit_dist_overlap(xs, ys, Overlap::SuffixPrefix, 1).unwrap();
+        assert_eq!(dist, 1);
+        assert_eq!((region.xstart, region.xend), (6, 14));
+        assert_eq!((region.ystart, region.yend), (0, 8));
+        assert_eq!(region.ylen(), 8);
+        assert_eq!(aln.to_string(), "====X===");
+        let (dist, region, _) = edit_dist_overlap(ys, xs, Overlap::PrefixSuffix, 1).unwrap();
+        assert_eq!(dist, 1);
+        assert_eq!((region.xstart, region.xend), (0, 8));
+        assert_eq!((region.ystart, region.yend), (6, 14));
+        let (dist, region, _) = edit_dist_overlap(xs, ys, Overlap::SuffixPrefix, 0).unwrap();
+        assert_eq!(dist, 0);
+        assert_eq!(region.ylen(), 4);
+        assert!(edit_dist_overlap(b"AAAA", b"CCCC", Overlap::SuffixPrefix, 0).is_none());
+    }
 }
@@ -1,4 +1,5 @@
 use super::alignments::*;
+use crate::match_policy::MatchPolicy;
 
 #[derive(Debug, Clone, Copy)]
 struct OpDist(u64);
@@ -79,7 +80,19 @@ const ON_GAP_FLAG: u64 = 0b1111_1111 << 8;
 const PREV_OF_FLAG: u64 = 0b1111_1111;
 
 /// Usual edit distance alignments and its path.
-pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
+pub fn edit_dist<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    edit_dist_by(xs, ys, |x, y| x == y)
+}
+
+/// Same as `edit_dist`, but the bases are compared by `policy`.
+pub fn edit_dist_with_policy(xs: &[u8], ys: &[u8], policy: MatchPolicy) -> (u32, Alignment) {
+    edit_dist_by(xs, ys, |&x, &y| policy.matches(x, y))
+}
+
+fn edit_dist_by<T, F>(xs: &[T], ys: &[T], eq: F) -> (u32, Alignment)
+where
+    F: Fn(&T, &T) -> bool,
+{
     let max = (xs.len() + ys.len() + 3) as u32;
     let mut dp = vec![vec![OpDist::init(max); ys.len() + 1]; xs.len() + 1];
     for i in 1..xs.len() + 1 {
@@ -89,9 +102,9 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
         dp[0][j] = OpDist::new(j as u32, 1, Op::Ins, Op::Ins);
     }
     dp[0][0] = OpDist::init(0);
-    for (i, x) in xs.iter().enumerate().map(|(i, &x)| (i + 1, x)) {
-        for (j, y) in ys.iter().enumerate().map(|(j, &y)| (j + 1, y)) {
-            let mat_score = dp[i - 1][j - 1].mat_move(x == y);
+    for (i, x) in xs.iter().enumerate().map(|(i, x)| (i + 1, x)) {
+        for (j, y) in ys.iter().enumerate().map(|(j, y)| (j + 1, y)) {
+            let mat_score = dp[i - 1][j - 1].mat_move(eq(x, y));
             let del_score = dp[i - 1][j].del_move();
             let ins_score = dp[i][j - 1].ins_move();
             dp[i][j] = mat_score.min(del_score).min(ins_score);
@@ -122,6 +135,207 @@ pub fn edit_dist(xs: &[u8], ys: &[u8]) -> (u32, Alignment) {
     (dist, aln)
 }
 
+/// Same as `edit_dist`, but only the cells within `band` diagonals of the main diagonals are computed,
+/// where the main diagonals are the ones between the start and the end of the DP matrix.
+/// If the distance is less than `2 * (band + 1) + |xs.len() - ys.len()|`,
+/// it is optimal and the alignment is the same as `edit_dist`. Otherwise, it is an upper bound.
+pub fn edit_dist_banded<T: Eq>(xs: &[T], ys: &[T], band: usize) -> (u32, Alignment) {
+    let dp = BandedDp::new(xs, ys, band, u32::MAX).unwrap();
+    let (dist, _, aln) = traceback(|i, j| dp.get(i, j), xs.len(), ys.len());
+    (dist, aln)
+}
+
+/// Same as `edit_dist`, by Ukkonen's band doubling.
+/// Starting from a narrow band, `edit_dist_banded` is repeated with doubled bands until the result is provably optimal.
+pub fn edit_dist_band_doubling<T: Eq>(xs: &[T], ys: &[T]) -> (u32, Alignment) {
+    let len_diff = xs.len().abs_diff(ys.len());
+    let mut band = 8;
+    loop {
+        let dp = BandedDp::new(xs, ys, band, u32::MAX).unwrap();
+        let dist = dp.get(xs.len(), ys.len()).score() as usize;
+        if dist < 2 * (band + 1) + len_diff || xs.len().max(ys.len()) <= band {
+            let (dist, _, aln) = traceback(|i, j| dp.get(i, j), xs.len(), ys.len());
+            return (dist, aln);
+        }
+        band *= 2;
+    }
+}
+
+// The DP matrix restricted to the diagonals k = j - i in [lo, hi].
+struct BandedDp {
+    lo: isize,
+    hi: isize,
+    ylen: usize,
+    outside: OpDist,
+    // rows[i][k - lo] is the cell (i, i + k).
+    rows: Vec<Vec<OpDist>>,
+}
+
+impl BandedDp {
+    // Returns `None` as soon as every cell of a row exceeds `max_dist`.
+    fn new<T: Eq>(xs: &[T], ys: &[T], band: usize, max_dist: u32) -> Option<Self> {
+        let band = band.min(xs.len().max(ys.len()));
+        let len_diff = ys.len() as isize - xs.len() as isize;
+        let (lo, hi) = (
+            len_diff.min(0) - band as isize,
+            len_diff.max(0) + band as isize,
+        );
+        let outside = OpDist::init((xs.len() + ys.len() + 3) as u32);
+        let width = (hi - lo + 1) as usize;
+        let mut dp = Self {
+            lo,
+            hi,
+            ylen: ys.len(),
+            outside,
+            rows: Vec::with_capacity(xs.len() + 1),
+        };
+        for i in 0..xs.len() + 1 {
+            dp.rows.push(vec![outside; width]);
+            let start = (i as isize + lo).max(0) as usize;
+            let end = (i as isize + hi + 1).clamp(0, ys.len() as isize + 1) as usize;
+            for j in start..end {
+                let cell = match (i, j) {
+                    (0, 0) => OpDist::init(0),
+                    (0, _) => OpDist::new(j as u32, 1, Op::Ins, Op::Ins),
+                    (_, 0) => OpDist::new(i as u32, 1, Op::Del, Op::Del),
+                    _ => {
+                        let mat_score = dp.get(i - 1, j - 1).mat_move(xs[i - 1] == ys[j - 1]);
+                        let del_score = dp.get(i - 1, j).del_move();
+                        let ins_score = dp.get(i, j - 1).ins_move();
+                        mat_score.min(del_score).min(ins_score)
+                    }
+                };
+                dp.rows[i][(j as isize - i as isize - lo) as usize] = cell;
+            }
+            if dp.rows[i].iter().all(|cell| max_dist < cell.score()) {
+                return None;
+            }
+        }
+        Some(dp)
+    }
+    fn get(&self, i: usize, j: usize) -> OpDist {
+        let k = j as isize - i as isize;
+        match self.lo <= k && k <= self.hi && j <= self.ylen {
+            true => self.rows[i][(k - self.lo) as usize],
+            false => self.outside,
+        }
+    }
+}
+
+/// Same as `edit_dist`, but returns `None` if the distance exceeds `max_dist`.
+/// Only the band of diagonals that an alignment within `max_dist` can pass is computed, as `edit_dist_banded`,
+/// and it returns `None` as soon as every cell of a row in the band exceeds `max_dist`.
+pub fn edit_dist_bounded<T: Eq>(xs: &[T], ys: &[T], max_dist: u32) -> Option<(u32, Alignment)> {
+    let len_diff = xs.len().abs_diff(ys.len());
+    let band = (max_dist as usize).checked_sub(len_diff)? / 2;
+    let dp = BandedDp::new(xs, ys, band, max_dist)?;
+    let (dist, _, aln) = traceback(|i, j| dp.get(i, j), xs.len(), ys.len());
+    (dist <= max_dist).then_some((dist, aln))
+}
+
+/// Semi-global edit distance, minimizing the number of gaps among the optimal alignments.
+/// The end gaps flagged in `end_gaps` are free.
+/// Returns the distance, the aligned region, and the alignment of the region only.
+pub fn edit_dist_semi_global<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    end_gaps: EndGaps,
+) -> (u32, Region, Alignment) {
+    let dp = fill_semi_global(xs, ys, end_gaps);
+    let last_row = (0..ys.len() + 1).map(|j| (xs.len(), j));
+    let last_col = (0..xs.len() + 1).map(|i| (i, ys.len()));
+    let (xend, yend) = last_row
+        .chain(last_col)
+        .filter(|&(i, j)| end_gaps.is_end(i, j, xs.len(), ys.len()))
+        .min_by_key(|&(i, j)| (dp[i][j].score(), dp[i][j].num_gaps()))
+        .unwrap();
+    traceback(|i, j| dp[i][j], xend, yend)
+}
+
+/// The longest overlap of xs and ys in the given orientation with at most `max_dist` edits,
+/// minimizing the number of gaps. The length is measured on the sequence contributing its prefix.
+/// Returns the distance, the overlapping region, and its alignment, or `None` if they do not overlap.
+pub fn edit_dist_overlap<T: Eq>(
+    xs: &[T],
+    ys: &[T],
+    overlap: Overlap,
+    max_dist: u32,
+) -> Option<(u32, Region, Alignment)> {
+    let dp = fill_semi_global(xs, ys, overlap.end_gaps());
+    let (xend, yend) = match overlap {
+        Overlap::SuffixPrefix => (1..ys.len() + 1)
+            .rev()
+            .map(|j| (xs.len(), j))
+            .find(|&(i, j)| dp[i][j].score() <= max_dist)?,
+        Overlap::PrefixSuffix => (1..xs.len() + 1)
+            .rev()
+            .map(|i| (i, ys.len()))
+            .find(|&(i, j)| dp[i][j].score() <= max_dist)?,
+    };
+    Some(traceback(|i, j| dp[i][j], xend, yend))
+}
+
+fn fill_semi_global<T: Eq>(xs: &[T], ys: &[T], end_gaps: EndGaps) -> Vec<Vec<OpDist>> {
+    let max = (xs.len() + ys.len() + 3) as u32;
+    let mut dp = vec![vec![OpDist::init(max); ys.len() + 1]; xs.len() + 1];
+    for (i, row) in dp.iter_mut().enumerate().skip(1) {
+        row[0] = match end_gaps.xs_start {
+            true => OpDist::init(0),
+            false => OpDist::new(i as u32, 1, Op::Del, Op::Del),
+        };
+    }
+    for (j, cell) in dp[0].iter_mut().enumerate().skip(1) {
+        *cell = match end_gaps.ys_start {
+            true => OpDist::init(0),
+            false => OpDist::new(j as u32, 1, Op::Ins, Op::Ins),
+        };
+    }
+    dp[0][0] = OpDist::init(0);
+    for (i, x) in xs.iter().enumerate().map(|(i, x)| (i + 1, x)) {
+        for (j, y) in ys.iter().enumerate().map(|(j, y)| (j + 1, y)) {
+            let mat_score = dp[i - 1][j - 1].mat_move(x == y);
+            let del_score = dp[i - 1][j].del_move();
+            let ins_score = dp[i][j - 1].ins_move();
+            dp[i][j] = mat_score.min(del_score).min(ins_score);
+        }
+    }
+    dp
+}
+
+fn traceback<F>(dp: F, xend: usize, yend: usize) -> (u32, Region, Alignment)
+where
+    F: Fn(usize, usize) -> OpDist,
+{
+    let (mut xpos, mut ypos) = (xend, yend);
+    let mut last = dp(xend, yend);
+    let dist = last.score();
+    let num_gaps = last.num_gaps();
+    let mut ops = vec![];
+    while let Some(op) = last.prev_op() {
+        ops.push(op);
+        match op {
+            Op::Match | Op::Mismatch => {
+                xpos -= 1;
+                ypos -= 1;
+            }
+            Op::Ins => ypos -= 1,
+            Op::Del => xpos -= 1,
+        }
+        last = dp(xpos, ypos);
+    }
+    ops.reverse();
+    let aln = Alignment::new(ops);
+    let (_, gaps) = aln.dist_and_num_of_gaps();
+    assert_eq!(gaps, num_gaps, "{:?}", aln);
+    let region = Region {
+        xstart: xpos,
+        xend,
+        ystart: ypos,
+        yend,
+    };
+    (dist, region, aln)
+}
+
 #[cfg(test)]
 mod tests {
     use rand::SeedableRng;
@@ -139,4 +353,99 @@ mod tests {
             assert_eq!(dist_1, dist_2)
         }
     }
+    #[test]
+    fn banded_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.1, 0.05, 0.05);
+        for len in [0, 1, 10, 100, 300] {
+            for _ in 0..20 {
+                let xs = kiley::gen_seq::generate_seq(&mut rng, len);
+                let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+                let expected = super::edit_dist(&xs, &ys);
+                assert_eq!(super::edit_dist_band_doubling(&xs, &ys), expected);
+                assert_eq!(
+                    super::edit_dist_band_doubling(&ys, &xs),
+                    super::edit_dist(&ys, &xs)
+                );
+                let (dist, aln) = super::edit_dist_banded(&xs, &ys, 2);
+                assert!(expected.0 <= dist);
+                assert_eq!(aln.validate(&xs, &ys), Ok(()));
+                let band = xs.len().max(ys.len());
+                assert_eq!(super::edit_dist_banded(&xs, &ys, band), expected);
+            }
+        }
+    }
+    #[test]
+    fn bounded_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let expected = super::edit_dist(&xs, &ys);
+            for max_dist in 0..expected.0 + 2 {
+                let bounded = super::edit_dist_bounded(&xs, &ys, max_dist);
+                match max_dist < expected.0 {
+                    true => assert!(bounded.is_none()),
+                    false => assert_eq!(bounded.as_ref(), Some(&expected)),
+                }
+            }
+            let unbounded = super::edit_dist_bounded(&xs, &ys, u32::MAX);
+            assert_eq!(unbounded, Some(expected));
+        }
+    }
+    #[test]
+    fn semi_global_random() {
+        use crate::alignments::EndGaps;
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+            let seq2 = kiley::gen_seq::introduce_randomness(&seq, &mut rng, &prof);
+            let seq2 = &seq2[rng.gen_range(0..5)..];
+            let end_gaps = EndGaps {
+                xs_start: rng.gen_bool(0.5),
+                xs_end: rng.gen_bool(0.5),
+                ys_start: rng.gen_bool(0.5),
+                ys_end: rng.gen_bool(0.5),
+            };
+            use crate::edit_dist_usual::edit_dist_semi_global as usual;
+            let (dist_1, _, _) = usual(&seq, seq2, end_gaps);
+            let (dist_2, region, aln) = super::edit_dist_semi_global(&seq, seq2, end_gaps);
+            assert_eq!(dist_1, dist_2);
+            let xs = &seq[region.xstart..region.xend];
+            let ys = &seq2[region.ystart..region.yend];
+            assert_eq!(aln.validate(xs, ys), Ok(()));
+            assert_eq!(aln.dist_and_num_of_gaps().0, dist_2);
+        }
+    }
+    #[test]
+    fn overlap_random() {
+        use crate::alignments::Overlap;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let seq = kiley::gen_seq::generate_seq(&mut rng, 60);
+            let xs = &seq[..40];
+            let ys = kiley::gen_seq::introduce_randomness(&seq[20..], &mut rng, &prof);
+            for (overlap, xs, ys) in [
+                (Overlap::SuffixPrefix, xs, ys.as_slice()),
+                (Overlap::PrefixSuffix, ys.as_slice(), xs),
+            ] {
+                use crate::edit_dist_usual::edit_dist_overlap as usual;
+                let (dist_1, region_1, _) = usual(xs, ys, overlap, 5).unwrap();
+                let (dist_2, region, aln) = super::edit_dist_overlap(xs, ys, overlap, 5).unwrap();
+                assert_eq!((dist_1, region_1.yend), (dist_2, region.yend));
+                let xs = &xs[region.xstart..region.xend];
+                let ys = &ys[region.ystart..region.yend];
+                assert_eq!(aln.validate(xs, ys), Ok(()));
+                assert_eq!(aln.dist_and_num_of_gaps().0, dist_2);
+            }
+        }
+    }
 }
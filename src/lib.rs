@@ -1,6 +1,110 @@
+//! Pairwise sequence alignments by several algorithms.
+//! The aligners in `edit_dist_usual`, `edit_dist_usual_clean`, `edit_dist_diff`, `edit_dist_diff_clean`,
+//! and `affine_gap` take slices of any type with equality, e.g., bytes, chars, or lines,
+//! except for the functions taking a `MatchPolicy` or a `SubstitutionMatrix`.
+//! `edit_dist_bit_parallel`, `affine_gap_diff`, and the other modules work on bytes only.
 pub mod affine_gap;
+pub mod affine_gap_diff;
+pub mod algebra;
+pub mod alignment_result;
 pub mod alignments;
+pub mod edit_dist_bit_parallel;
 pub mod edit_dist_diff;
 pub mod edit_dist_diff_clean;
 pub mod edit_dist_usual;
 pub mod edit_dist_usual_clean;
+pub mod liftover;
+pub mod match_policy;
+pub mod normalize;
+pub mod pretty_print;
+pub mod run_length;
+pub mod sam_tags;
+pub mod stats;
+pub mod substitution_matrix;
+
+#[cfg(test)]
+mod tests {
+    use crate::alignments::Alignment;
+    type Aligner<T, S> = (&'static str, fn(&[T], &[T]) -> (S, Alignment));
+    // The generic edit distance aligners, instantiated for `T`.
+    fn edit_dist_aligners<T: Eq>() -> Vec<Aligner<T, u32>> {
+        vec![
+            ("usual", crate::edit_dist_usual::edit_dist),
+            ("usual linear", crate::edit_dist_usual::edit_dist_linear),
+            ("usual bounded", |xs, ys| {
+                crate::edit_dist_usual::edit_dist_bounded(xs, ys, 10).unwrap()
+            }),
+            ("usual clean", crate::edit_dist_usual_clean::edit_dist),
+            (
+                "usual clean band doubling",
+                crate::edit_dist_usual_clean::edit_dist_band_doubling,
+            ),
+            ("usual clean bounded", |xs, ys| {
+                crate::edit_dist_usual_clean::edit_dist_bounded(xs, ys, 10).unwrap()
+            }),
+            ("diff", crate::edit_dist_diff::edit_dist),
+            ("diff bounded", |xs, ys| {
+                crate::edit_dist_diff::edit_dist_bounded(xs, ys, 10).unwrap()
+            }),
+            ("diff clean", crate::edit_dist_diff_clean::edit_dist),
+            (
+                "diff clean linear",
+                crate::edit_dist_diff_clean::edit_dist_linear,
+            ),
+            ("diff clean bounded", |xs, ys| {
+                crate::edit_dist_diff_clean::edit_dist_bounded(xs, ys, 10).unwrap()
+            }),
+        ]
+    }
+    // The generic affine gap aligners, instantiated for `T`.
+    fn affine_aligners<T: Eq>() -> Vec<Aligner<T, i64>> {
+        vec![
+            ("affine", |xs, ys| {
+                crate::affine_gap::align(xs, ys, 1, -1, -2, -1)
+            }),
+            ("affine linear", |xs, ys| {
+                crate::affine_gap::align_linear(xs, ys, 1, -1, -2, -1).unwrap()
+            }),
+        ]
+    }
+    #[test]
+    fn generic_elements() {
+        let xs = ["fn main() {", "    let x = 1;", "    println!(x);", "}"];
+        let ys = [
+            "fn main() {",
+            "    let x = 2;",
+            "    let y = 3;",
+            "    println!(x);",
+            "}",
+        ];
+        for (name, aligner) in edit_dist_aligners() {
+            let (dist, aln) = aligner(&xs, &ys);
+            assert_eq!((dist, aln.dist_and_num_of_gaps().0), (2, 2), "{}", name);
+        }
+        for (name, aligner) in affine_aligners() {
+            assert_eq!(aligner(&xs, &ys).0, 3 - 1 - 2, "{}", name);
+        }
+        let xs: Vec<char> = "kitten".chars().collect();
+        let ys: Vec<char> = "sitting".chars().collect();
+        for (name, aligner) in edit_dist_aligners() {
+            assert_eq!(aligner(&xs, &ys).0, 3, "{}", name);
+        }
+        // The results on bytes do not change when they are mapped to other tokens.
+        let xs = b"ACGTTTACGACGAC";
+        let ys = b"ACGTACGGACGTAC";
+        let to_ids = |xs: &[u8]| -> Vec<u32> { xs.iter().map(|&x| x as u32 * 1000).collect() };
+        let (xids, yids) = (to_ids(xs), to_ids(ys));
+        let aligners = edit_dist_aligners::<u8>()
+            .into_iter()
+            .zip(edit_dist_aligners::<u32>());
+        for ((name, on_bytes), (_, on_ids)) in aligners {
+            assert_eq!(on_bytes(xs, ys), on_ids(&xids, &yids), "{}", name);
+        }
+        let aligners = affine_aligners::<u8>()
+            .into_iter()
+            .zip(affine_aligners::<u32>());
+        for ((name, on_bytes), (_, on_ids)) in aligners {
+            assert_eq!(on_bytes(xs, ys), on_ids(&xids, &yids), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! This module projects positions and intervals between the reference and the query through an Alignment.
+//! Positions are 0-based, and intervals are half-open.
+use crate::alignments::*;
+
+/// What to do with a position without a counterpart,
+/// i.e., a reference base in a deletion, or a query base in an insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// Report it as unmapped (`None`).
+    Unmapped,
+    /// Map it to the nearest aligned base on the left flank.
+    Left,
+    /// Map it to the nearest aligned base on the right flank.
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+    Ref,
+    Query,
+}
+
+impl Side {
+    pub(crate) fn consumes(&self, op: Op) -> bool {
+        match self {
+            Side::Ref => op != Op::Ins,
+            Side::Query => op != Op::Del,
+        }
+    }
+    fn other(&self) -> Self {
+        match self {
+            Side::Ref => Side::Query,
+            Side::Query => Side::Ref,
+        }
+    }
+}
+
+impl Alignment {
+    /// Map the `pos`-th base of the reference to the query. `None` if it is out of range or unmapped.
+    pub fn ref_to_query(&self, pos: usize, policy: GapPolicy) -> Option<usize> {
+        self.lift(pos, Side::Ref, policy)
+    }
+    /// Map the `pos`-th base of the query to the reference. `None` if it is out of range or unmapped.
+    pub fn query_to_ref(&self, pos: usize, policy: GapPolicy) -> Option<usize> {
+        self.lift(pos, Side::Query, policy)
+    }
+    /// Map the reference interval `[start, end)` to the query interval covered by the same columns.
+    /// Insertions flanking the interval are not included.
+    /// A fully deleted interval maps to an empty interval. `None` if the interval is empty or out of range.
+    pub fn ref_to_query_interval(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        self.lift_interval(start, end, Side::Ref)
+    }
+    /// Map the query interval `[start, end)` to the reference, as `ref_to_query_interval`.
+    pub fn query_to_ref_interval(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        self.lift_interval(start, end, Side::Query)
+    }
+    fn lift(&self, pos: usize, from: Side, policy: GapPolicy) -> Option<usize> {
+        let to = from.other();
+        let column = self.column_of(pos, from)?;
+        // The number of bases of the other side before a column.
+        let lifted = |column: usize| {
+            let ops = &self.ops[..column];
+            ops.iter().filter(|&&op| to.consumes(op)).count()
+        };
+        if to.consumes(self.ops[column]) {
+            return Some(lifted(column));
+        }
+        // Otherwise, the nearest aligned column, skipping the other indels.
+        let aligned = |op: &Op| *op == Op::Match || *op == Op::Mismatch;
+        match policy {
+            GapPolicy::Unmapped => None,
+            GapPolicy::Left => self.ops[..column].iter().rposition(aligned).map(lifted),
+            GapPolicy::Right => {
+                let next = self.ops[column + 1..].iter().position(aligned);
+                next.map(|offset| lifted(column + 1 + offset))
+            }
+        }
+    }
+    fn lift_interval(&self, start: usize, end: usize, from: Side) -> Option<(usize, usize)> {
+        if end <= start {
+            return None;
+        }
+        let to = from.other();
+        let first = self.column_of(start, from)?;
+        let last = self.column_of(end - 1, from)?;
+        let count = |ops: &[Op]| ops.iter().filter(|&&op| to.consumes(op)).count();
+        let lifted_start = count(&self.ops[..first]);
+        let lifted_end = lifted_start + count(&self.ops[first..last + 1]);
+        Some((lifted_start, lifted_end))
+    }
+    // The column consuming the `pos`-th base of `side`.
+    pub(crate) fn column_of(&self, pos: usize, side: Side) -> Option<usize> {
+        self.ops
+            .iter()
+            .enumerate()
+            .filter(|&(_, &op)| side.consumes(op))
+            .nth(pos)
+            .map(|(column, _)| column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn lift_position() {
+        // Ref:   ACGTA--CGTAC
+        // Query: ACCAAGG--T-C
+        let aln = Alignment::from_str("==XX=IIDD=D=").unwrap();
+        use GapPolicy::*;
+        assert_eq!(aln.ref_to_query(0, Unmapped), Some(0));
+        assert_eq!(aln.ref_to_query(4, Unmapped), Some(4));
+        assert_eq!(aln.ref_to_query(5, Unmapped), None);
+        // The neighbours are aligned bases, not the inserted ones.
+        assert_eq!(aln.ref_to_query(5, Left), Some(4));
+        assert_eq!(aln.ref_to_query(6, Left), Some(4));
+        assert_eq!(aln.ref_to_query(5, Right), Some(7));
+        assert_eq!(aln.ref_to_query(7, Unmapped), Some(7));
+        assert_eq!(aln.ref_to_query(9, Unmapped), Some(8));
+        assert_eq!(aln.ref_to_query(10, Unmapped), None);
+        assert_eq!(aln.query_to_ref(5, Unmapped), None);
+        assert_eq!(aln.query_to_ref(5, Left), Some(4));
+        assert_eq!(aln.query_to_ref(5, Right), Some(7));
+        assert_eq!(aln.query_to_ref(6, Left), Some(4));
+        assert_eq!(aln.query_to_ref(6, Right), Some(7));
+        assert_eq!(aln.query_to_ref(7, Unmapped), Some(7));
+        let aln = Alignment::from_str("D==I").unwrap();
+        assert_eq!(aln.ref_to_query(0, Left), None);
+        assert_eq!(aln.ref_to_query(0, Right), Some(0));
+        assert_eq!(aln.query_to_ref(2, Right), None);
+        assert_eq!(aln.query_to_ref(2, Left), Some(2));
+    }
+    #[test]
+    fn lift_interval() {
+        let aln = Alignment::from_str("==XX=IIDD=D=").unwrap();
+        assert_eq!(aln.ref_to_query_interval(0, 5), Some((0, 5)));
+        assert_eq!(aln.ref_to_query_interval(4, 6), Some((4, 7)));
+        assert_eq!(aln.ref_to_query_interval(5, 7), Some((7, 7)));
+        assert_eq!(aln.ref_to_query_interval(5, 10), Some((7, 9)));
+        assert_eq!(aln.ref_to_query_interval(3, 3), None);
+        assert_eq!(aln.ref_to_query_interval(8, 11), None);
+        assert_eq!(aln.query_to_ref_interval(5, 7), Some((5, 5)));
+        assert_eq!(aln.query_to_ref_interval(4, 8), Some((4, 8)));
+    }
+}
@@ -0,0 +1,138 @@
+//! This module defines when two bases are regarded as a match.
+//! Only the functions taking a `MatchPolicy` use it, both to score and to label `Match`/`Mismatch` columns.
+//! They are `edit_dist_with_policy` of the four edit distance modules and `affine_gap::align_with_policy`,
+//! with `validate_with_policy` and `recover_with_policy` to check and show their alignments.
+//! Every other aligner compares the bases exactly. See `Algorithm` for the entry points of each module.
+
+/// How the bases are compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchPolicy {
+    /// Two bases match if they are the same byte.
+    #[default]
+    Exact,
+    /// Two bases match if they are the same letter, ignoring ASCII case.
+    /// Use it to align soft-masked (lowercase) sequences, and see `Alignment::stats_with_mask`.
+    CaseInsensitive,
+    /// Two IUPAC nucleotide codes match if they share a base, e.g., `N` matches any base and `R` matches `A` and `G`.
+    /// The case is ignored, `U` is `T`, and the other bytes match as `CaseInsensitive`.
+    Iupac,
+}
+
+impl MatchPolicy {
+    pub fn matches(&self, x: u8, y: u8) -> bool {
+        match self {
+            MatchPolicy::Exact => x == y,
+            MatchPolicy::CaseInsensitive => x.eq_ignore_ascii_case(&y),
+            MatchPolicy::Iupac => match (iupac_bases(x), iupac_bases(y)) {
+                (Some(x), Some(y)) => x & y != 0,
+                _ => x.eq_ignore_ascii_case(&y),
+            },
+        }
+    }
+}
+
+// The set of bases of an IUPAC code, as A = 1, C = 2, G = 4, and T = 8.
+fn iupac_bases(code: u8) -> Option<u8> {
+    let bases = match code.to_ascii_uppercase() {
+        b'A' => 0b0001,
+        b'C' => 0b0010,
+        b'G' => 0b0100,
+        b'T' | b'U' => 0b1000,
+        b'R' => 0b0101,
+        b'Y' => 0b1010,
+        b'S' => 0b0110,
+        b'W' => 0b1001,
+        b'K' => 0b1100,
+        b'M' => 0b0011,
+        b'B' => 0b1110,
+        b'D' => 0b1101,
+        b'H' => 0b1011,
+        b'V' => 0b0111,
+        b'N' => 0b1111,
+        _ => return None,
+    };
+    Some(bases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alignments::*;
+    use rand::SeedableRng;
+    #[test]
+    fn matches() {
+        use MatchPolicy::*;
+        assert!(Exact.matches(b'A', b'A'));
+        assert!(!Exact.matches(b'a', b'A'));
+        assert!(CaseInsensitive.matches(b'a', b'A'));
+        assert!(!CaseInsensitive.matches(b'N', b'A'));
+        assert!(Iupac.matches(b'N', b'A'));
+        assert!(Iupac.matches(b'r', b'G'));
+        assert!(!Iupac.matches(b'R', b'C'));
+        assert!(Iupac.matches(b'Y', b'S'));
+        assert!(Iupac.matches(b'U', b't'));
+        assert!(Iupac.matches(b'-', b'-'));
+        assert!(!Iupac.matches(b'-', b'N'));
+    }
+    #[test]
+    fn aligners_agree() {
+        use rand::Rng;
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        let codes = b"ACGTRYSWKMBDHVNacgtn";
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 40);
+            let mut ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            // Mask some bases by ambiguity codes.
+            for y in ys.iter_mut() {
+                if rng.gen_bool(0.1) {
+                    *y = codes[rng.gen_range(0..codes.len())];
+                }
+            }
+            for policy in [
+                MatchPolicy::Exact,
+                MatchPolicy::CaseInsensitive,
+                MatchPolicy::Iupac,
+            ] {
+                let (dist, aln) = crate::edit_dist_usual::edit_dist_with_policy(&xs, &ys, policy);
+                assert_eq!(aln.validate_with_policy(&xs, &ys, policy), Ok(()));
+                let alns = [
+                    crate::edit_dist_usual_clean::edit_dist_with_policy(&xs, &ys, policy),
+                    crate::edit_dist_diff::edit_dist_with_policy(&xs, &ys, policy),
+                    crate::edit_dist_diff_clean::edit_dist_with_policy(&xs, &ys, policy),
+                ];
+                for (dist_2, aln) in alns {
+                    assert_eq!(dist, dist_2);
+                    assert_eq!(aln.validate_with_policy(&xs, &ys, policy), Ok(()));
+                    assert_eq!(aln.dist_and_num_of_gaps().0, dist);
+                }
+                let (score, aln) =
+                    crate::affine_gap::align_with_policy(&xs, &ys, 0, -1, -1, -1, policy);
+                assert_eq!(score, -(dist as i64));
+                assert_eq!(aln.validate_with_policy(&xs, &ys, policy), Ok(()));
+                let (_, ops, _) = aln.recover_with_policy(&xs, &ys, policy);
+                let num_match = ops.iter().filter(|&&c| c == b'|').count();
+                assert_eq!(
+                    num_match,
+                    aln.ops.iter().filter(|&&op| op == Op::Match).count()
+                );
+            }
+        }
+        let aln = Alignment::from_str("==X=").unwrap();
+        let (_, ops, _) = aln.recover_with_policy(b"ANGT", b"ACCT", MatchPolicy::Iupac);
+        assert_eq!(ops, b"||X|");
+        let aln = Alignment::from_str("=X==").unwrap();
+        let err = aln.validate_with_policy(b"ANGT", b"ACGT", MatchPolicy::Iupac);
+        let expected = ValidationError::NotMismatch {
+            column: 1,
+            x: b'N',
+            y: b'C',
+        };
+        assert_eq!(err, Err(expected.clone()));
+        assert_eq!(
+            expected.to_string(),
+            "column 1 is a mismatch, but N matches C"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! This module moves indels to their leftmost (or rightmost) equivalent positions.
+//! A run of insertions or deletions is shifted over a match only if the shifted column is still a match,
+//! so the edit distance and the set of mismatches do not change.
+//! At a tie, i.e., adjacent insertions and deletions, they are put in a fixed order, deletions first.
+use crate::alignments::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+impl Alignment {
+    /// Shift every indel to its leftmost or rightmost equivalent position. xs is the reference, ys is the query.
+    pub fn normalize(&self, xs: &[u8], ys: &[u8], direction: Direction) -> Self {
+        match direction {
+            Direction::Left => Self::new(left_normalize(&self.ops, xs, ys, Op::Del)),
+            Direction::Right => {
+                let rev = |xs: &[u8]| -> Vec<u8> { xs.iter().rev().copied().collect() };
+                let ops: Vec<_> = self.ops.iter().rev().copied().collect();
+                // Reversed, the deletions come last.
+                let mut ops = left_normalize(&ops, &rev(xs), &rev(ys), Op::Ins);
+                ops.reverse();
+                Self::new(ops)
+            }
+        }
+    }
+    /// True if the two alignments of xs and ys differ only by the placement of indels.
+    pub fn is_equivalent(&self, other: &Self, xs: &[u8], ys: &[u8]) -> bool {
+        let aln = self.normalize(xs, ys, Direction::Left);
+        let other = other.normalize(xs, ys, Direction::Left);
+        aln == other
+    }
+}
+
+// Shifts the indels to the left and puts `first` first in adjacent indels, until neither changes the alignment.
+fn left_normalize(ops: &[Op], xs: &[u8], ys: &[u8], first: Op) -> Vec<Op> {
+    let mut ops = ops.to_vec();
+    loop {
+        let mut shifted = shift_left(&ops, xs, ys);
+        sort_indels(&mut shifted, first);
+        if shifted == ops {
+            return ops;
+        }
+        ops = shifted;
+    }
+}
+
+fn sort_indels(ops: &mut [Op], first: Op) {
+    let is_indel = |op: &Op| *op == Op::Ins || *op == Op::Del;
+    for block in ops.split_mut(|op| !is_indel(op)) {
+        block.sort_by_key(|&op| op != first);
+    }
+}
+
+fn shift_left(ops: &[Op], xs: &[u8], ys: &[u8]) -> Vec<Op> {
+    let mut ops = ops.to_vec();
+    let mut column = 0;
+    // The reference and query position at `column`.
+    let (mut i, mut j) = (0, 0);
+    while column < ops.len() {
+        let op = ops[column];
+        if op == Op::Match || op == Op::Mismatch {
+            column += 1;
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (mut start, mut end) = (column, column);
+        while end < ops.len() && ops[end] == op {
+            end += 1;
+        }
+        let len = end - start;
+        while 0 < start && ops[start - 1] == Op::Match {
+            // The preceding match aligns xs[i-1] and ys[j-1].
+            let still_match = match op {
+                Op::Del => xs[i + len - 1] == ys[j - 1],
+                _ => xs[i - 1] == ys[j + len - 1],
+            };
+            // Do not merge with the previous run of the same operation.
+            let merge = 1 < start && ops[start - 2] == op;
+            if !still_match || merge {
+                break;
+            }
+            ops[start - 1..end].rotate_left(1);
+            (start, end) = (start - 1, end - 1);
+            (i, j) = (i - 1, j - 1);
+        }
+        match op {
+            Op::Del => i += len,
+            _ => j += len,
+        }
+        column = end;
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    #[test]
+    fn normalize() {
+        let xs = b"ACAAAT";
+        let ys = b"ACAAT";
+        let aln = Alignment::from_str("====D=").unwrap();
+        let left = aln.normalize(xs, ys, Direction::Left);
+        assert_eq!(left.to_string(), "==D===");
+        let right = left.normalize(xs, ys, Direction::Right);
+        assert_eq!(right.to_string(), "====D=");
+        let xs = b"TACACG";
+        let ys = b"TACG";
+        let aln = Alignment::from_str("===DD=").unwrap();
+        let left = aln.normalize(xs, ys, Direction::Left);
+        assert_eq!(left.to_string(), "=DD===");
+        let aln = Alignment::from_str("=DD===").unwrap();
+        assert_eq!(
+            aln.normalize(xs, ys, Direction::Right).to_string(),
+            "===DD="
+        );
+        let xs = b"TACG";
+        let ys = b"TACACG";
+        let aln = Alignment::from_str("===II=").unwrap();
+        assert!(aln.is_equivalent(&Alignment::from_str("=II===").unwrap(), xs, ys));
+        assert!(!aln.is_equivalent(&Alignment::from_str("IIX===").unwrap(), xs, ys));
+        // A mismatch blocks the shift.
+        let xs = b"AAGAT";
+        let ys = b"AACT";
+        let aln = Alignment::from_str("==XD=").unwrap();
+        assert_eq!(aln.normalize(xs, ys, Direction::Left), aln);
+        // Adjacent insertions and deletions are ordered, deletions first.
+        let xs = b"AGT";
+        let ys = b"ACT";
+        let aln = Alignment::from_str("=ID=").unwrap();
+        for dir in [Direction::Left, Direction::Right] {
+            assert_eq!(aln.normalize(xs, ys, dir).to_string(), "=DI=");
+        }
+        assert!(aln.is_equivalent(&Alignment::from_str("=DI=").unwrap(), xs, ys));
+        // The deletion shifts next to the insertion, and then they are ordered.
+        let xs = b"TAAG";
+        let ys = b"TCAG";
+        let aln = Alignment::from_str("=I=D=").unwrap();
+        let left = aln.normalize(xs, ys, Direction::Left);
+        assert_eq!(left.to_string(), "=DI==");
+    }
+    #[test]
+    fn normalize_random() {
+        use rand_xoshiro::Xoroshiro128Plus;
+        let mut rng: Xoroshiro128Plus = SeedableRng::seed_from_u64(32909);
+        let prof = kiley::gen_seq::Profile::new(0.05, 0.05, 0.05);
+        for _ in 0..100 {
+            let xs = kiley::gen_seq::generate_seq(&mut rng, 30);
+            let ys = kiley::gen_seq::introduce_randomness(&xs, &mut rng, &prof);
+            let (_, aln) = crate::edit_dist_usual::edit_dist(&xs, &ys);
+            for dir in [Direction::Left, Direction::Right] {
+                let norm = aln.normalize(&xs, &ys, dir);
+                assert_eq!(norm.validate(&xs, &ys), Ok(()));
+                assert_eq!(norm.dist_and_num_of_gaps(), aln.dist_and_num_of_gaps());
+                assert_eq!(norm.normalize(&xs, &ys, dir), norm);
+            }
+            let left = aln.normalize(&xs, &ys, Direction::Left);
+            let right = aln.normalize(&xs, &ys, Direction::Right);
+            assert!(left.is_equivalent(&right, &xs, &ys));
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! This module formats an Alignment for human review, in the BLAST/EMBOSS style.
+//! Each block has the reference row, the match row, and the query row, with 1-based inclusive coordinates.
+//! A row with gaps only shows the empty range `s s`, where `s` is the last position before it.
+use crate::alignments::*;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+impl Alignment {
+    /// Format the alignment, wrapping every `width` columns. xs is the reference, ys is the query.
+    /// Gaps are shown as `-`. If `width` is zero, the alignment is not wrapped.
+    /// If `color` is true, matches, mismatches, and indels are colored by ANSI escape codes.
+    pub fn pretty_print(&self, xs: &[u8], ys: &[u8], width: usize, color: bool) -> String {
+        let (xr, aln, yr) = self.recover(xs, ys);
+        let width = if width == 0 { aln.len().max(1) } else { width };
+        let digits = format!("{}", xs.len().max(ys.len())).len();
+        let (mut xpos, mut ypos) = (0, 0);
+        let mut blocks = vec![];
+        for ((xr, aln), yr) in xr
+            .chunks(width)
+            .zip(aln.chunks(width))
+            .zip(yr.chunks(width))
+        {
+            let xlen = xr.iter().zip(aln).filter(|&(&x, &a)| !is_gap(x, a)).count();
+            let ylen = yr.iter().zip(aln).filter(|&(&y, &a)| !is_gap(y, a)).count();
+            let xrow = format_row(xr, aln, b'-', color);
+            // The trailing gap columns are trimmed from the match row.
+            let matched = aln.len() - aln.iter().rev().take_while(|&&a| a == b' ').count();
+            let arow = format_row(&aln[..matched], aln, b' ', color);
+            let arow = format!("      {:>w$} {}", "", arow, w = digits);
+            let yrow = format_row(yr, aln, b'-', color);
+            let (xend, yend) = (xpos + xlen, ypos + ylen);
+            let start = |pos: usize, len: usize| if len == 0 { pos } else { pos + 1 };
+            let block = format!(
+                "Ref   {:>w$} {} {}\n{}\nQuery {:>w$} {} {}\n",
+                start(xpos, xlen),
+                xrow,
+                xend,
+                arow.trim_end(),
+                start(ypos, ylen),
+                yrow,
+                yend,
+                w = digits
+            );
+            blocks.push(block);
+            (xpos, ypos) = (xend, yend);
+        }
+        blocks.join("\n")
+    }
+}
+
+fn is_gap(base: u8, aln: u8) -> bool {
+    aln == b' ' && base == b' '
+}
+
+fn format_row(row: &[u8], aln: &[u8], gap: u8, color: bool) -> String {
+    let mut formatted = String::new();
+    let mut current = None;
+    for (&base, &a) in row.iter().zip(aln) {
+        let base = if is_gap(base, a) { gap } else { base } as char;
+        if color {
+            let code = match a {
+                b'|' => GREEN,
+                b'X' => RED,
+                _ => YELLOW,
+            };
+            if current != Some(code) {
+                formatted.push_str(code);
+                current = Some(code);
+            }
+        }
+        formatted.push(base);
+    }
+    if current.is_some() {
+        formatted.push_str(RESET);
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn pretty_print() {
+        let xs = b"ACGTACGTAC";
+        let ys = b"ACCAAGGTCTC";
+        let aln = Alignment::from_str("==XX=IIDD=D=II").unwrap();
+        let formatted = aln.pretty_print(xs, ys, 6, false);
+        let expected = [
+            "Ref    1 ACGTA- 5",
+            "         ||XX|",
+            "Query  1 ACCAAG 6",
+            "",
+            "Ref    6 -CGTAC 10",
+            "            | |",
+            "Query  7 G--T-C 9",
+            "",
+            "Ref   10 -- 10",
+            "",
+            "Query 10 TC 11",
+        ];
+        assert_eq!(formatted.lines().collect::<Vec<_>>(), expected);
+        let whole = aln.pretty_print(xs, ys, 0, false);
+        assert_eq!(
+            whole,
+            "Ref    1 ACGTA--CGTAC-- 10\n         ||XX|    | |\nQuery  1 ACCAAGG--T-CTC 11\n"
+        );
+        let colored = aln.pretty_print(xs, ys, 0, true);
+        assert!(colored.starts_with("Ref    1 \x1b[32mAC\x1b[31mGT\x1b[32mA\x1b[33m--"));
+        let match_row = colored.lines().nth(1).unwrap();
+        assert!(match_row.ends_with("\x1b[32m|\x1b[33m \x1b[32m|\x1b[0m"));
+    }
+}
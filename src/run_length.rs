@@ -0,0 +1,140 @@
+//! This module defines the run-length encoded alignment.
+//! Each run is an operation and the number of times it repeats,
+//! so a long read with few edits is stored in a handful of runs instead of one byte per column.
+use crate::alignments::*;
+use crate::match_policy::MatchPolicy;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunLengthAlignment {
+    // Adjacent runs always have different operations and no run is empty.
+    runs: Vec<(Op, u32)>,
+}
+
+impl RunLengthAlignment {
+    pub fn new(runs: Vec<(Op, u32)>) -> Self {
+        let mut aln = Self::default();
+        for (op, len) in runs {
+            aln.push(op, len);
+        }
+        aln
+    }
+    /// Append `len` columns of `op`, merging with the last run if possible.
+    pub fn push(&mut self, op: Op, len: u32) {
+        if len == 0 {
+            return;
+        }
+        match self.runs.last_mut() {
+            Some((last, last_len)) if *last == op => *last_len += len,
+            _ => self.runs.push((op, len)),
+        }
+    }
+    pub fn runs(&self) -> &[(Op, u32)] {
+        &self.runs
+    }
+    /// The number of columns of the alignment.
+    pub fn len(&self) -> usize {
+        self.runs.iter().map(|&(_, len)| len as usize).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+    pub fn reverse(&mut self) {
+        self.runs.reverse();
+    }
+    /// Iterate over the operations column by column, without expanding the alignment.
+    pub fn iter(&self) -> impl Iterator<Item = Op> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|&(op, len)| (0..len).map(move |_| op))
+    }
+    pub fn dist_and_num_of_gaps(&self) -> (u32, u32) {
+        let dist = self
+            .runs
+            .iter()
+            .filter(|&&(op, _)| op != Op::Match)
+            .map(|&(_, len)| len)
+            .sum();
+        // As adjacent runs differ, every indel run opens a new gap.
+        let num_of_gap = self
+            .runs
+            .iter()
+            .filter(|&&(op, _)| op == Op::Ins || op == Op::Del)
+            .count() as u32;
+        (dist, num_of_gap)
+    }
+    /// xs is the reference, ys is the query.
+    pub fn recover(&self, xs: &[u8], ys: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        recover_ops(self.iter(), xs, ys, MatchPolicy::Exact)
+    }
+    /// Same as `recover`, but the bases are compared by `policy`.
+    pub fn recover_with_policy(
+        &self,
+        xs: &[u8],
+        ys: &[u8],
+        policy: MatchPolicy,
+    ) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        recover_ops(self.iter(), xs, ys, policy)
+    }
+}
+
+impl std::convert::From<&Alignment> for RunLengthAlignment {
+    fn from(aln: &Alignment) -> Self {
+        let mut rle = Self::default();
+        for &op in aln.ops.iter() {
+            rle.push(op, 1);
+        }
+        rle
+    }
+}
+
+impl std::convert::From<&RunLengthAlignment> for Alignment {
+    fn from(rle: &RunLengthAlignment) -> Self {
+        let mut ops = Vec::with_capacity(rle.len());
+        for &(op, len) in rle.runs.iter() {
+            ops.resize(ops.len() + len as usize, op);
+        }
+        Alignment::new(ops)
+    }
+}
+
+impl std::convert::From<RunLengthAlignment> for Alignment {
+    fn from(rle: RunLengthAlignment) -> Self {
+        Alignment::from(&rle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn convert() {
+        let aln = Alignment::from_str("XX=====IDIDIDIDID====XXXXIDIDDDD").unwrap();
+        let rle = RunLengthAlignment::from(&aln);
+        assert_eq!(
+            rle.runs()[..3],
+            [(Op::Mismatch, 2), (Op::Match, 5), (Op::Ins, 1)]
+        );
+        assert_eq!(rle.len(), aln.ops.len());
+        assert_eq!(rle.iter().collect::<Vec<_>>(), aln.ops);
+        assert_eq!(Alignment::from(&rle).ops, aln.ops);
+        assert_eq!(rle.dist_and_num_of_gaps(), aln.dist_and_num_of_gaps());
+        let rle = RunLengthAlignment::new(vec![(Op::Match, 2), (Op::Del, 0), (Op::Match, 3)]);
+        assert_eq!(rle.runs(), &[(Op::Match, 5)]);
+        assert!(RunLengthAlignment::new(vec![]).is_empty());
+    }
+    #[test]
+    fn recover() {
+        let xs = b"ACGTACGT";
+        let ys = b"ACCAAGGT";
+        let aln = Alignment::from_str("==XX=IIDD=").unwrap();
+        let rle = RunLengthAlignment::from(&aln);
+        assert_eq!(rle.recover(xs, ys), aln.recover(xs, ys));
+        let ys = b"ACSAAGGT";
+        let (_, ops, _) = rle.recover_with_policy(xs, ys, MatchPolicy::Iupac);
+        assert_eq!(&ops[..4], b"|||X");
+        assert_eq!(
+            rle.recover_with_policy(xs, ys, MatchPolicy::Iupac),
+            aln.recover_with_policy(xs, ys, MatchPolicy::Iupac)
+        );
+    }
+}
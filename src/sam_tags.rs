@@ -0,0 +1,117 @@
+//! This module builds the SAM `MD`/`NM` tags and the minimap2 `cs` tag from an Alignment.
+//! Only the values are returned, e.g., `10A5^AC6` rather than `MD:Z:10A5^AC6`.
+use crate::alignments::*;
+
+impl Alignment {
+    /// The value of the `NM:i` tag, the number of mismatches, inserted bases and deleted bases.
+    pub fn nm_tag(&self) -> u32 {
+        self.ops.iter().filter(|&&op| op != Op::Match).count() as u32
+    }
+    /// The value of the `MD:Z` tag. xs is the reference.
+    /// Insertions do not appear in the tag and do not break the count of matches.
+    pub fn md_tag(&self, xs: &[u8]) -> String {
+        let mut md = String::new();
+        let (mut i, mut matches) = (0, 0);
+        let mut prev = None;
+        for &op in self.ops.iter() {
+            match op {
+                Op::Match => matches += 1,
+                Op::Mismatch => {
+                    md.push_str(&format!("{}{}", matches, xs[i] as char));
+                    matches = 0;
+                }
+                Op::Del if prev == Some(Op::Del) => md.push(xs[i] as char),
+                Op::Del => {
+                    md.push_str(&format!("{}^{}", matches, xs[i] as char));
+                    matches = 0;
+                }
+                Op::Ins => {}
+            }
+            if op != Op::Ins {
+                i += 1;
+            }
+            prev = Some(op);
+        }
+        md.push_str(&format!("{}", matches));
+        md
+    }
+    /// The value of the minimap2 `cs` tag. xs is the reference, ys is the query.
+    /// If `long` is true, matches are written out as `=ACGT` instead of `:4`.
+    pub fn cs_tag(&self, xs: &[u8], ys: &[u8], long: bool) -> String {
+        let mut cs = String::new();
+        let (mut i, mut j, mut matches) = (0, 0, 0);
+        let mut prev = None;
+        for &op in self.ops.iter() {
+            if op != Op::Match && 0 < matches {
+                cs.push_str(&format!(":{}", matches));
+                matches = 0;
+            }
+            match op {
+                Op::Match if long => {
+                    if prev != Some(Op::Match) {
+                        cs.push('=');
+                    }
+                    cs.push(xs[i].to_ascii_uppercase() as char);
+                }
+                Op::Match => matches += 1,
+                Op::Mismatch => {
+                    cs.push('*');
+                    cs.push(xs[i].to_ascii_lowercase() as char);
+                    cs.push(ys[j].to_ascii_lowercase() as char);
+                }
+                Op::Del => {
+                    if prev != Some(Op::Del) {
+                        cs.push('-');
+                    }
+                    cs.push(xs[i].to_ascii_lowercase() as char);
+                }
+                Op::Ins => {
+                    if prev != Some(Op::Ins) {
+                        cs.push('+');
+                    }
+                    cs.push(ys[j].to_ascii_lowercase() as char);
+                }
+            }
+            match op {
+                Op::Match | Op::Mismatch => (i, j) = (i + 1, j + 1),
+                Op::Del => i += 1,
+                Op::Ins => j += 1,
+            }
+            prev = Some(op);
+        }
+        if 0 < matches {
+            cs.push_str(&format!(":{}", matches));
+        }
+        cs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn md_and_nm() {
+        let xs = b"ACGTACGTAC";
+        let aln = Alignment::from_str("==XX=IIDD=D=II").unwrap();
+        assert_eq!(aln.md_tag(xs), "2G0T1^CG1^A1");
+        assert_eq!(aln.nm_tag(), 9);
+        let (dist, _) = aln.dist_and_num_of_gaps();
+        assert_eq!(aln.nm_tag(), dist);
+        let aln = Alignment::from_str("DID===").unwrap();
+        assert_eq!(aln.md_tag(b"ACGTA"), "0^A0^C3");
+        let aln = Alignment::from_str("====").unwrap();
+        assert_eq!(aln.md_tag(b"ACGT"), "4");
+        assert_eq!(Alignment::new(vec![]).md_tag(b""), "0");
+    }
+    #[test]
+    fn cs() {
+        let xs = b"ACGTACGTAC";
+        let ys = b"ACCAAGGTCTC";
+        let aln = Alignment::from_str("==XX=IIDD=D=II").unwrap();
+        assert_eq!(aln.cs_tag(xs, ys, false), ":2*gc*ta:1+gg-cg:1-a:1+tc");
+        assert_eq!(aln.cs_tag(xs, ys, true), "=AC*gc*ta=A+gg-cg=T-a=C+tc");
+        let aln = Alignment::from_str("=====").unwrap();
+        assert_eq!(aln.cs_tag(b"acgta", b"acgta", false), ":5");
+        assert_eq!(aln.cs_tag(b"acgta", b"acgta", true), "=ACGTA");
+    }
+}
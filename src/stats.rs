@@ -0,0 +1,206 @@
+//! This module computes summary statistics of an Alignment in a single pass over its operations.
+//! Optionally, the columns in soft-masked (lowercase) sequence are counted separately.
+use crate::alignments::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlignmentStats {
+    pub num_match: usize,
+    pub num_mismatch: usize,
+    /// The number of inserted bases.
+    pub num_ins: usize,
+    /// The number of deleted bases.
+    pub num_del: usize,
+    pub num_ins_open: usize,
+    pub num_del_open: usize,
+    pub longest_ins: usize,
+    pub longest_del: usize,
+    /// `gap_hist[len]` is the number of insertions and deletions of length `len`.
+    pub gap_hist: Vec<usize>,
+    /// The number of reference bases in the alignment.
+    pub ref_span: usize,
+    /// The number of query bases in the alignment.
+    pub query_span: usize,
+    /// The statistics of the masked columns. Only filled by `stats_with_mask`.
+    pub masked: Option<MaskedStats>,
+}
+
+/// The statistics of the columns with a soft-masked (lowercase) base in the reference or the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaskedStats {
+    pub num_match: usize,
+    pub num_mismatch: usize,
+    pub num_ins: usize,
+    pub num_del: usize,
+    /// The number of masked reference bases in the alignment.
+    pub ref_masked: usize,
+    /// The number of masked query bases in the alignment.
+    pub query_masked: usize,
+}
+
+impl MaskedStats {
+    /// The number of masked columns.
+    pub fn aln_len(&self) -> usize {
+        self.num_match + self.num_mismatch + self.num_ins + self.num_del
+    }
+    /// The number of masked columns other than matches.
+    pub fn num_diffs(&self) -> usize {
+        self.num_mismatch + self.num_ins + self.num_del
+    }
+}
+
+impl AlignmentStats {
+    /// The number of columns.
+    pub fn aln_len(&self) -> usize {
+        self.num_match + self.num_mismatch + self.num_ins + self.num_del
+    }
+    /// BLAST identity, # of matches over # of columns.
+    pub fn blast_identity(&self) -> f64 {
+        match self.aln_len() {
+            0 => 0f64,
+            len => self.num_match as f64 / len as f64,
+        }
+    }
+    /// Gap-compressed identity, where each gap counts as one difference regardless of its length.
+    pub fn gap_compressed_identity(&self) -> f64 {
+        let len = self.num_match + self.num_mismatch + self.num_ins_open + self.num_del_open;
+        match len {
+            0 => 0f64,
+            len => self.num_match as f64 / len as f64,
+        }
+    }
+    /// The fraction of the columns in masked sequence. `None` if the mask is not counted.
+    pub fn masked_fraction(&self) -> Option<f64> {
+        let masked = self.masked.as_ref()?;
+        match self.aln_len() {
+            0 => Some(0f64),
+            len => Some(masked.aln_len() as f64 / len as f64),
+        }
+    }
+    fn close_gap(&mut self, op: Op, len: usize) {
+        match op {
+            Op::Ins => self.longest_ins = self.longest_ins.max(len),
+            Op::Del => self.longest_del = self.longest_del.max(len),
+            _ => return,
+        }
+        if self.gap_hist.len() <= len {
+            self.gap_hist.resize(len + 1, 0);
+        }
+        self.gap_hist[len] += 1;
+    }
+}
+
+impl Alignment {
+    pub fn stats(&self) -> AlignmentStats {
+        let mut stats = AlignmentStats::default();
+        let mut run = None;
+        for &op in self.ops.iter() {
+            match op {
+                Op::Match => stats.num_match += 1,
+                Op::Mismatch => stats.num_mismatch += 1,
+                Op::Ins => stats.num_ins += 1,
+                Op::Del => stats.num_del += 1,
+            }
+            run = match run {
+                Some((prev, len)) if prev == op => Some((op, len + 1)),
+                Some((prev, len)) => {
+                    stats.close_gap(prev, len);
+                    Some((op, 1))
+                }
+                None => Some((op, 1)),
+            };
+            if run == Some((Op::Ins, 1)) {
+                stats.num_ins_open += 1;
+            }
+            if run == Some((Op::Del, 1)) {
+                stats.num_del_open += 1;
+            }
+        }
+        if let Some((prev, len)) = run {
+            stats.close_gap(prev, len);
+        }
+        stats.ref_span = stats.num_match + stats.num_mismatch + stats.num_del;
+        stats.query_span = stats.num_match + stats.num_mismatch + stats.num_ins;
+        stats
+    }
+    /// Same as `stats`, but also counts the columns in soft-masked (lowercase) bases.
+    /// xs is the reference, ys is the query. It panics if the alignment does not fit the sequences.
+    pub fn stats_with_mask(&self, xs: &[u8], ys: &[u8]) -> AlignmentStats {
+        let mut stats = self.stats();
+        let mut masked = MaskedStats::default();
+        let (mut i, mut j) = (0, 0);
+        for &op in self.ops.iter() {
+            let x_masked = op != Op::Ins && xs[i].is_ascii_lowercase();
+            let y_masked = op != Op::Del && ys[j].is_ascii_lowercase();
+            masked.ref_masked += x_masked as usize;
+            masked.query_masked += y_masked as usize;
+            if x_masked || y_masked {
+                match op {
+                    Op::Match => masked.num_match += 1,
+                    Op::Mismatch => masked.num_mismatch += 1,
+                    Op::Ins => masked.num_ins += 1,
+                    Op::Del => masked.num_del += 1,
+                }
+            }
+            i += (op != Op::Ins) as usize;
+            j += (op != Op::Del) as usize;
+        }
+        stats.masked = Some(masked);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stats() {
+        let aln = Alignment::from_str("==XX=IIDD=D=IIIDDD").unwrap();
+        let stats = aln.stats();
+        assert_eq!(stats.num_match, 5);
+        assert_eq!(stats.num_mismatch, 2);
+        assert_eq!((stats.num_ins, stats.num_del), (5, 6));
+        assert_eq!((stats.num_ins_open, stats.num_del_open), (2, 3));
+        assert_eq!((stats.longest_ins, stats.longest_del), (3, 3));
+        assert_eq!(stats.gap_hist, vec![0, 1, 2, 2]);
+        assert_eq!((stats.ref_span, stats.query_span), (13, 12));
+        assert_eq!(stats.aln_len(), aln.ops.len());
+        assert!((stats.blast_identity() - 5f64 / 18f64).abs() < 0.0001);
+        assert!((stats.gap_compressed_identity() - 5f64 / 12f64).abs() < 0.0001);
+        let (dist, gaps) = aln.dist_and_num_of_gaps();
+        let open = stats.num_ins_open + stats.num_del_open;
+        assert_eq!(dist as usize, stats.aln_len() - stats.num_match);
+        assert_eq!(gaps as usize, open);
+        assert_eq!(stats.masked_fraction(), None);
+        let stats = Alignment::new(vec![]).stats();
+        assert_eq!(stats.blast_identity(), 0f64);
+        assert!(stats.gap_hist.is_empty());
+    }
+    #[test]
+    fn stats_with_mask() {
+        use crate::match_policy::MatchPolicy;
+        let xs = b"ACGTacgtacGTAC";
+        let ys = b"ACGTACTACGTAC";
+        // The masked bases never match the unmasked bases by default.
+        let (dist, _) = crate::edit_dist_usual::edit_dist(xs, ys);
+        assert!(1 < dist);
+        let (dist, aln_ci) =
+            crate::edit_dist_usual::edit_dist_with_policy(xs, ys, MatchPolicy::CaseInsensitive);
+        assert_eq!(dist, 1);
+        assert_eq!(aln_ci.to_string(), "======D=======");
+        let stats = aln_ci.stats_with_mask(xs, ys);
+        let masked = stats.masked.clone().unwrap();
+        assert_eq!((masked.num_match, masked.num_del), (5, 1));
+        assert_eq!((masked.num_mismatch, masked.num_ins), (0, 0));
+        assert_eq!((masked.ref_masked, masked.query_masked), (6, 0));
+        assert_eq!(masked.num_diffs(), 1);
+        assert!((stats.masked_fraction().unwrap() - 6f64 / 14f64).abs() < 0.0001);
+        assert_eq!(
+            AlignmentStats {
+                masked: None,
+                ..stats
+            },
+            aln_ci.stats()
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! This module defines substitution matrices, the score of aligning each pair of symbols.
+//! Built-in matrices are provided for DNA and proteins, and others can be loaded from the NCBI text format.
+
+/// A substitution matrix over byte symbols.
+/// A lowercase letter is scored as its uppercase letter, unless the matrix has the lowercase letter itself.
+/// A symbol not in the matrix is scored as `*` against a symbol in the matrix if the matrix has `*`,
+/// and two symbols not in the matrix are scored by the lowest score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutionMatrix {
+    // symbol -> the index of its row and column.
+    index: Vec<Option<usize>>,
+    // The index of `*`, if any.
+    unknown: Option<usize>,
+    size: usize,
+    scores: Vec<i64>,
+    lowest: i64,
+}
+
+impl SubstitutionMatrix {
+    // `scores` is the row-major matrix over `symbols`.
+    fn new(symbols: &[u8], scores: Vec<i64>) -> Self {
+        let size = symbols.len();
+        assert_eq!(scores.len(), size * size);
+        let mut index = vec![None; 256];
+        for (i, &symbol) in symbols.iter().enumerate() {
+            index[symbol as usize] = Some(i);
+        }
+        for upper in b'A'..=b'Z' {
+            let lower = upper.to_ascii_lowercase() as usize;
+            if index[lower].is_none() {
+                index[lower] = index[upper as usize];
+            }
+        }
+        let unknown = index[b'*' as usize];
+        let lowest = scores.iter().copied().min().unwrap_or(0);
+        Self {
+            index,
+            unknown,
+            size,
+            scores,
+            lowest,
+        }
+    }
+    /// The score of aligning `x` and `y`.
+    pub fn score(&self, x: u8, y: u8) -> i64 {
+        let (x, y) = (self.index[x as usize], self.index[y as usize]);
+        match (x.or(self.unknown), y.or(self.unknown)) {
+            (Some(i), Some(j)) if x.is_some() || y.is_some() => self.scores[i * self.size + j],
+            _ => self.lowest,
+        }
+    }
+    /// True if aligning `x` and `y` scores as much as aligning each of them with itself,
+    /// e.g., a lowercase letter and its uppercase letter. The aligners label such pairs as matches.
+    pub fn is_match(&self, x: u8, y: u8) -> bool {
+        let score = self.score(x, y);
+        score == self.score(x, x) && score == self.score(y, y)
+    }
+    /// The lowest score in the matrix.
+    pub fn lowest(&self) -> i64 {
+        self.lowest
+    }
+    /// A DNA matrix over `ACGT`, scoring transitions (A/G, C/T) and transversions differently.
+    pub fn dna(mat_score: i64, transition: i64, transversion: i64) -> Self {
+        let symbols = b"ACGT";
+        let is_purine = |x: u8| x == b'A' || x == b'G';
+        let scores = symbols
+            .iter()
+            .flat_map(|&x| symbols.iter().map(move |&y| (x, y)))
+            .map(|(x, y)| match (x == y, is_purine(x) == is_purine(y)) {
+                (true, _) => mat_score,
+                (false, true) => transition,
+                (false, false) => transversion,
+            })
+            .collect();
+        Self::new(symbols, scores)
+    }
+    /// BLOSUM62, as distributed by NCBI.
+    pub fn blosum62() -> Self {
+        Self::from_ncbi(BLOSUM62).unwrap()
+    }
+    /// PAM250, as distributed by NCBI.
+    pub fn pam250() -> Self {
+        Self::from_ncbi(PAM250).unwrap()
+    }
+    /// Parse a matrix in the NCBI text format: `#` comments, a header line of the column symbols,
+    /// and a line for each row, starting with its symbol followed by the scores.
+    pub fn from_ncbi(text: &str) -> Result<Self, MatrixError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (header_line, header) = lines.next().ok_or(MatrixError::MissingHeader)?;
+        let symbol = |token: &str, line: usize| match token.as_bytes() {
+            &[symbol] => Ok(symbol),
+            _ => Err(MatrixError::InvalidSymbol { line }),
+        };
+        let symbols: Vec<u8> = header
+            .split_whitespace()
+            .map(|token| symbol(token, header_line))
+            .collect::<Result<_, _>>()?;
+        if symbols
+            .iter()
+            .enumerate()
+            .any(|(i, s)| symbols[..i].contains(s))
+        {
+            return Err(MatrixError::InvalidSymbol { line: header_line });
+        }
+        let size = symbols.len();
+        let mut rows: Vec<Option<Vec<i64>>> = vec![None; size];
+        for (line_num, line) in lines {
+            let mut fields = line.split_whitespace();
+            let symbol = symbol(fields.next().unwrap(), line_num)?;
+            let row: Vec<i64> = fields
+                .map(|score| score.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| MatrixError::InvalidScore { line: line_num })?;
+            let idx = symbols.iter().position(|&s| s == symbol);
+            let idx = idx.ok_or(MatrixError::UnknownRow { line: line_num })?;
+            if row.len() != size {
+                return Err(MatrixError::RowLength { line: line_num });
+            }
+            if rows[idx].replace(row).is_some() {
+                return Err(MatrixError::DuplicateRow { line: line_num });
+            }
+        }
+        let mut scores = Vec::with_capacity(size * size);
+        for (row, &symbol) in rows.into_iter().zip(symbols.iter()) {
+            let symbol = symbol as char;
+            scores.extend(row.ok_or(MatrixError::MissingRow { symbol })?);
+        }
+        Ok(Self::new(&symbols, scores))
+    }
+}
+
+/// Errors raised when parsing a substitution matrix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatrixError {
+    /// There is no header line of the column symbols.
+    MissingHeader,
+    /// A symbol at `line` is not a single byte, or the header has it twice.
+    InvalidSymbol { line: usize },
+    /// A score at `line` is not an integer.
+    InvalidScore { line: usize },
+    /// The row at `line` does not have one score for each column.
+    RowLength { line: usize },
+    /// The symbol of the row at `line` is not in the header.
+    UnknownRow { line: usize },
+    /// The row at `line` repeats the symbol of an earlier row.
+    DuplicateRow { line: usize },
+    /// The column `symbol` has no row.
+    MissingRow { symbol: char },
+}
+
+impl std::fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatrixError::MissingHeader => write!(f, "missing header line"),
+            MatrixError::InvalidSymbol { line } => write!(f, "invalid symbol at line {}", line),
+            MatrixError::InvalidScore { line } => write!(f, "invalid score at line {}", line),
+            MatrixError::RowLength { line } => write!(f, "wrong number of scores at line {}", line),
+            MatrixError::UnknownRow { line } => write!(f, "row not in the header at line {}", line),
+            MatrixError::DuplicateRow { line } => write!(f, "duplicate row at line {}", line),
+            MatrixError::MissingRow { symbol } => write!(f, "missing row for {}", symbol),
+        }
+    }
+}
+
+impl std::error::Error for MatrixError {}
+
+const BLOSUM62: &str = "\
+#  Matrix made by matblas from blosum62.iij
+#  * column uses minimum score
+#  BLOSUM Clustered Scoring Matrix in 1/2 Bit Units
+#  Blocks Database = /data/blocks_5.0/blocks.dat
+#  Cluster Percentage: >= 62
+#  Entropy =   0.6979, Expected =  -0.5209
+   A  R  N  D  C  Q  E  G  H  I  L  K  M  F  P  S  T  W  Y  V  B  Z  X  *
+A  4 -1 -2 -2  0 -1 -1  0 -2 -1 -1 -1 -1 -2 -1  1  0 -3 -2  0 -2 -1  0 -4
+R -1  5  0 -2 -3  1  0 -2  0 -3 -2  2 -1 -3 -2 -1 -1 -3 -2 -3 -1  0 -1 -4
+N -2  0  6  1 -3  0  0  0  1 -3 -3  0 -2 -3 -2  1  0 -4 -2 -3  3  0 -1 -4
+D -2 -2  1  6 -3  0  2 -1 -1 -3 -4 -1 -3 -3 -1  0 -1 -4 -3 -3  4  1 -1 -4
+C  0 -3 -3 -3  9 -3 -4 -3 -3 -1 -1 -3 -1 -2 -3 -1 -1 -2 -2 -1 -3 -3 -2 -4
+Q -1  1  0  0 -3  5  2 -2  0 -3 -2  1  0 -3 -1  0 -1 -2 -1 -2  0  3 -1 -4
+E -1  0  0  2 -4  2  5 -2  0 -3 -3  1 -2 -3 -1  0 -1 -3 -2 -2  1  4 -1 -4
+G  0 -2  0 -1 -3 -2 -2  6 -2 -4 -4 -2 -3 -3 -2  0 -2 -2 -3 -3 -1 -2 -1 -4
+H -2  0  1 -1 -3  0  0 -2  8 -3 -3 -1 -2 -1 -2 -1 -2 -2  2 -3  0  0 -1 -4
+I -1 -3 -3 -3 -1 -3 -3 -4 -3  4  2 -3  1  0 -3 -2 -1 -3 -1  3 -3 -3 -1 -4
+L -1 -2 -3 -4 -1 -2 -3 -4 -3  2  4 -2  2  0 -3 -2 -1 -2 -1  1 -4 -3 -1 -4
+K -1  2  0 -1 -3  1  1 -2 -1 -3 -2  5 -1 -3 -1  0 -1 -3 -2 -2  0  1 -1 -4
+M -1 -1 -2 -3 -1  0 -2 -3 -2  1  2 -1  5  0 -2 -1 -1 -1 -1  1 -3 -1 -1 -4
+F -2 -3 -3 -3 -2 -3 -3 -3 -1  0  0 -3  0  6 -4 -2 -2  1  3 -1 -3 -3 -1 -4
+P -1 -2 -2 -1 -3 -1 -1 -2 -2 -3 -3 -1 -2 -4  7 -1 -1 -4 -3 -2 -2 -1 -2 -4
+S  1 -1  1  0 -1  0  0  0 -1 -2 -2  0 -1 -2 -1  4  1 -3 -2 -2  0  0  0 -4
+T  0 -1  0 -1 -1 -1 -1 -2 -2 -1 -1 -1 -1 -2 -1  1  5 -2 -2  0 -1 -1  0 -4
+W -3 -3 -4 -4 -2 -2 -3 -2 -2 -3 -2 -3 -1  1 -4 -3 -2 11  2 -3 -4 -3 -2 -4
+Y -2 -2 -2 -3 -2 -1 -2 -3  2 -1 -1 -2 -1  3 -3 -2 -2  2  7 -1 -3 -2 -1 -4
+V  0 -3 -3 -3 -1 -2 -2 -3 -3  3  1 -2  1 -1 -2 -2  0 -3 -1  4 -3 -2 -1 -4
+B -2 -1  3  4 -3  0  1 -1  0 -3 -4  0 -3 -3 -2  0 -1 -4 -3 -3  4  1 -1 -4
+Z -1  0  0  1 -3  3  4 -2  0 -3 -3  1 -1 -3 -1  0 -1 -3 -2 -2  1  4 -1 -4
+X  0 -1 -1 -1 -2 -1 -1 -1 -1 -1 -1 -1 -1 -1 -2  0  0 -2 -1 -1 -1 -1 -1 -4
+* -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4  1
+";
+
+const PAM250: &str = "\
+#
+# This matrix was produced by \"pam\" Version 1.0.6 [28-Jul-93]
+#
+# PAM 250 substitution matrix, scale = ln(2)/3 = 0.231049
+#
+# Expected score = -0.844, Entropy = 0.354 bits
+#
+# Lowest score = -8, Highest score = 17
+#
+   A  R  N  D  C  Q  E  G  H  I  L  K  M  F  P  S  T  W  Y  V  B  Z  X  *
+A  2 -2  0  0 -2  0  0  1 -1 -1 -2 -1 -1 -3  1  1  1 -6 -3  0  0  0  0 -8
+R -2  6  0 -1 -4  1 -1 -3  2 -2 -3  3  0 -4  0  0 -1  2 -4 -2 -1  0 -1 -8
+N  0  0  2  2 -4  1  1  0  2 -2 -3  1 -2 -3  0  1  0 -4 -2 -2  2  1  0 -8
+D  0 -1  2  4 -5  2  3  1  1 -2 -4  0 -3 -6 -1  0  0 -7 -4 -2  3  3 -1 -8
+C -2 -4 -4 -5 12 -5 -5 -3 -3 -2 -6 -5 -5 -4 -3  0 -2 -8  0 -2 -4 -5 -3 -8
+Q  0  1  1  2 -5  4  2 -1  3 -2 -2  1 -1 -5  0 -1 -1 -5 -4 -2  1  3 -1 -8
+E  0 -1  1  3 -5  2  4  0  1 -2 -3  0 -2 -5 -1  0  0 -7 -4 -2  3  3 -1 -8
+G  1 -3  0  1 -3 -1  0  5 -2 -3 -4 -2 -3 -5  0  1  0 -7 -5 -1  0  0 -1 -8
+H -1  2  2  1 -3  3  1 -2  6 -2 -2  0 -2 -2  0 -1 -1 -3  0 -2  1  2 -1 -8
+I -1 -2 -2 -2 -2 -2 -2 -3 -2  5  2 -2  2  1 -2 -1  0 -5 -1  4 -2 -2 -1 -8
+L -2 -3 -3 -4 -6 -2 -3 -4 -2  2  6 -3  4  2 -3 -3 -2 -2 -1  2 -3 -3 -1 -8
+K -1  3  1  0 -5  1  0 -2  0 -2 -3  5  0 -5 -1  0  0 -3 -4 -2  1  0 -1 -8
+M -1  0 -2 -3 -5 -1 -2 -3 -2  2  4  0  6  0 -2 -2 -1 -4 -2  2 -2 -2 -1 -8
+F -3 -4 -3 -6 -4 -5 -5 -5 -2  1  2 -5  0  9 -5 -3 -3  0  7 -1 -4 -5 -2 -8
+P  1  0  0 -1 -3  0 -1  0  0 -2 -3 -1 -2 -5  6  1  0 -6 -5 -1 -1  0 -1 -8
+S  1  0  1  0  0 -1  0  1 -1 -1 -3  0 -2 -3  1  2  1 -2 -3 -1  0  0  0 -8
+T  1 -1  0  0 -2 -1  0  0 -1  0 -2  0 -1 -3  0  1  3 -5 -3  0  0 -1  0 -8
+W -6  2 -4 -7 -8 -5 -7 -7 -3 -5 -2 -3 -4  0 -6 -2 -5 17  0 -6 -5 -6 -4 -8
+Y -3 -4 -2 -4  0 -4 -4 -5  0 -1 -1 -4 -2  7 -5 -3 -3  0 10 -2 -3 -4 -2 -8
+V  0 -2 -2 -2 -2 -2 -2 -1 -2  4  2 -2  2 -1 -1 -1  0 -6 -2  4 -2 -2 -1 -8
+B  0 -1  2  3 -4  1  3  0  1 -2 -3  1 -2 -4 -1  0  0 -5 -3 -2  3  2 -1 -8
+Z  0  0  1  3 -5  3  3  0  2 -2 -3  0 -2 -5  0  0 -1 -6 -4 -2  2  3 -1 -8
+X  0 -1  0 -1 -3 -1 -1 -1 -1 -1 -1 -1 -1 -2 -1  0  0 -4 -2 -1 -1 -1 -1 -8
+* -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8  1
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn builtin() {
+        for matrix in [SubstitutionMatrix::blosum62(), SubstitutionMatrix::pam250()] {
+            let symbols = b"ARNDCQEGHILKMFPSTWYVBZX*";
+            for &x in symbols {
+                for &y in symbols {
+                    assert_eq!(matrix.score(x, y), matrix.score(y, x));
+                }
+            }
+        }
+        let blosum62 = SubstitutionMatrix::blosum62();
+        assert_eq!(blosum62.score(b'W', b'W'), 11);
+        assert_eq!(blosum62.score(b'A', b'R'), -1);
+        assert_eq!(blosum62.score(b'J', b'A'), -4);
+        assert_eq!(blosum62.lowest(), -4);
+        let pam250 = SubstitutionMatrix::pam250();
+        assert_eq!(pam250.score(b'W', b'W'), 17);
+        assert_eq!(pam250.score(b'C', b'W'), -8);
+        let dna = SubstitutionMatrix::dna(2, -1, -3);
+        assert_eq!(dna.score(b'A', b'A'), 2);
+        assert_eq!(dna.score(b'A', b'G'), -1);
+        assert_eq!(dna.score(b'C', b'T'), -1);
+        assert_eq!(dna.score(b'A', b'T'), -3);
+        assert_eq!(dna.score(b'N', b'A'), -3);
+    }
+    #[test]
+    fn unknown_symbols() {
+        let blosum62 = SubstitutionMatrix::blosum62();
+        // Lowercase letters are scored as uppercase ones.
+        assert_eq!(blosum62.score(b'a', b'c'), blosum62.score(b'A', b'C'));
+        assert_eq!(blosum62.score(b'w', b'W'), 11);
+        // Unknown symbols are `*` against known ones, and the lowest against each other.
+        assert_eq!(blosum62.score(b'J', b'A'), blosum62.score(b'*', b'A'));
+        assert_eq!(blosum62.score(b'*', b'*'), 1);
+        assert_eq!(blosum62.score(b'J', b'O'), blosum62.lowest());
+        assert_eq!(blosum62.score(b'J', b'J'), blosum62.lowest());
+        assert_eq!(blosum62.score(b'-', b'*'), blosum62.score(b'*', b'*'));
+        let dna = SubstitutionMatrix::dna(2, -1, -3);
+        assert_eq!(dna.score(b'a', b'A'), 2);
+        assert_eq!(dna.score(b'N', b'N'), dna.lowest());
+        assert!(dna.is_match(b'a', b'A'));
+        assert!(!dna.is_match(b'A', b'G'));
+        assert!(!blosum62.is_match(b'B', b'D'));
+    }
+    #[test]
+    fn from_ncbi() {
+        let text = "# comment\n   A  C\nA  1 -2\nC -2  1\n";
+        let matrix = SubstitutionMatrix::from_ncbi(text).unwrap();
+        assert_eq!(matrix.score(b'C', b'C'), 1);
+        assert_eq!(matrix.score(b'A', b'C'), -2);
+        use MatrixError::*;
+        let from_ncbi = SubstitutionMatrix::from_ncbi;
+        assert_eq!(from_ncbi("# only comments\n"), Err(MissingHeader));
+        assert_eq!(
+            from_ncbi(" A C\nA 1 x\nC 0 1"),
+            Err(InvalidScore { line: 2 })
+        );
+        assert_eq!(from_ncbi(" A C\nA 1\nC 0 1"), Err(RowLength { line: 2 }));
+        assert_eq!(from_ncbi(" A C\nG 1 0\nC 0 1"), Err(UnknownRow { line: 2 }));
+        assert_eq!(from_ncbi(" A C\nA 1 0\n"), Err(MissingRow { symbol: 'C' }));
+        assert_eq!(
+            from_ncbi(" AG C\nA 1 0\nC 0 1"),
+            Err(InvalidSymbol { line: 1 })
+        );
+        assert_eq!(
+            from_ncbi(" A A\nA 1 0\nA 0 1"),
+            Err(InvalidSymbol { line: 1 })
+        );
+        assert_eq!(
+            from_ncbi(" A C\nAC 1 0\nC 0 1"),
+            Err(InvalidSymbol { line: 2 })
+        );
+        assert_eq!(
+            from_ncbi(" A C\nA 1 0\nA 0 1\nC 0 1"),
+            Err(DuplicateRow { line: 3 })
+        );
+    }
+}